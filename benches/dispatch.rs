@@ -0,0 +1,69 @@
+//! Interpreter dispatch on arithmetic-heavy bodies. Both bodies run the
+//! same 31 instructions: `fused` is written as `local.get, local.get,
+//! i32.add` triples that the interpreter runs as one op, while `unfused`
+//! pushes every value before adding them up. `hooked` runs `fused` with a
+//! hook attached, which forces one dispatch per instruction.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use zod::runtime::hooks::Hooks;
+use zod::runtime::{Config, Instance};
+
+const TRIPLES: usize = 8;
+
+/// A module exporting `body` as `run`, an (i32, i32) -> i32 function.
+fn module(body: Vec<u8>) -> Vec<u8> {
+    let code = [vec![0x01, body.len() as u8 + 2, 0x00], body, vec![0x0b]].concat();
+    [
+        vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00], // header
+        vec![0x01, 0x07, 0x01, 0x60, 0x02, 0x7f, 0x7f, 0x01, 0x7f], // type section
+        vec![0x03, 0x02, 0x01, 0x00],                         // function section
+        vec![0x07, 0x07, 0x01, 0x03, 0x72, 0x75, 0x6e, 0x00, 0x00], // export section
+        vec![0x0a, code.len() as u8],
+        code,
+    ]
+    .concat()
+}
+
+fn fused() -> Vec<u8> {
+    module(
+        [
+            [0x20, 0x00, 0x20, 0x01, 0x6a].repeat(TRIPLES),
+            [0x6a].repeat(TRIPLES - 1),
+        ]
+        .concat(),
+    )
+}
+
+fn unfused() -> Vec<u8> {
+    module(
+        [
+            [0x20, 0x00, 0x20, 0x01].repeat(TRIPLES),
+            [0x6a].repeat(2 * TRIPLES - 1),
+        ]
+        .concat(),
+    )
+}
+
+struct Noop;
+
+impl Hooks for Noop {}
+
+fn dispatch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dispatch");
+    let mut hooked = Config::default();
+    hooked.hooks.push(Box::new(Noop));
+    let runs = [
+        ("fused", fused(), Config::default()),
+        ("unfused", unfused(), Config::default()),
+        ("hooked", fused(), hooked),
+    ];
+    for (name, binary, config) in runs {
+        let mut instance = Instance::new(binary, config).unwrap();
+        assert_eq!(Ok(24), instance.invoke("run", &[1, 2]));
+        group.bench_function(name, |b| b.iter(|| instance.invoke("run", &[1, 2])));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, dispatch);
+criterion_main!(benches);
@@ -0,0 +1,3 @@
+//! Links the `zod` library's C API into a shared library.
+
+pub use zod_rs::ffi::*;
@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zod::runtime::{self, Config, Instance};
+
+// Decoding, validating and instantiating any bytes must fail cleanly
+// rather than panic.
+fuzz_target!(|data: &[u8]| {
+    let _ = runtime::disassemble(data.to_vec());
+    let _ = runtime::validate(data.to_vec());
+    let _ = runtime::stats(data.to_vec());
+    if let Ok(mut instance) = Instance::new(data.to_vec(), Config::new().with_fuel(1000)) {
+        let _ = instance.invoke("add", &[1, 2]);
+    }
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Whatever the parser accepts, the compiler has to encode.
+fuzz_target!(|source: &str| {
+    if let Ok(module) = zod::parse(source) {
+        zod::compile(&module);
+    }
+});
@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zod::Module;
+
+// Decoding a compiled module gives back the module it was compiled from.
+fuzz_target!(|module: Module| {
+    if let Err(err) = zod::round_trip::binary(&module) {
+        panic!("{}", err);
+    }
+});
@@ -0,0 +1,49 @@
+/* C API of the zod runtime, built as a shared library by the `capi` crate.
+ *
+ * Objects returned by a function are owned by the caller until passed to
+ * the matching _free function. Functions that can fail take a
+ * `zod_error **error`, which may be NULL, and set it on failure. */
+
+#ifndef ZOD_H
+#define ZOD_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct zod_error zod_error;
+typedef struct zod_module zod_module;
+typedef struct zod_instance zod_instance;
+
+/* Parses and compiles a null-terminated source string. Returns NULL if it
+ * is not a valid module. */
+zod_module *zod_compile(const char *source, zod_error **error);
+
+/* The module's binary form, valid until the module is freed. */
+const uint8_t *zod_module_binary(const zod_module *module, size_t *len);
+
+void zod_module_free(zod_module *module);
+
+/* Instantiates a binary module. Returns NULL if it cannot be decoded. */
+zod_instance *zod_instantiate(const uint8_t *binary, size_t len, zod_error **error);
+
+/* Invokes an exported function with i32 arguments. Returns 0 and writes
+ * the result on success, or -1 on failure. */
+int32_t zod_invoke(zod_instance *instance, const char *func, const int32_t *args, size_t nargs,
+                   int32_t *result, zod_error **error);
+
+void zod_instance_free(zod_instance *instance);
+
+/* The error's message, valid until the error is freed. */
+const char *zod_error_message(const zod_error *error);
+
+void zod_error_free(zod_error *error);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
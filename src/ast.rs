@@ -1,4 +1,10 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
 #[derive(Debug, PartialEq, Clone, Copy, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ValueType {
     I32,
     I64,
@@ -9,12 +15,14 @@ pub type FuncType = (StackType, StackType);
 pub type Type = FuncType;
 
 #[derive(Debug, PartialEq, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Instr {
     LocalGet(usize),
     I32Add,
 }
 
 #[derive(Debug, PartialEq, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Func {
     pub f_type: i32,
     pub locals: Vec<ValueType>,
@@ -22,19 +30,303 @@ pub struct Func {
 }
 
 #[derive(Debug, PartialEq, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EDesc {
     FuncExport(usize),
 }
 
 #[derive(Debug, PartialEq, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Export {
     pub name: String,
     pub e_desc: EDesc,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Module {
     pub types: Vec<Type>,
     pub funcs: Vec<Func>,
     pub exports: Vec<Export>,
 }
+
+/// An exported function, with its index and type when the module
+/// declares them.
+#[derive(Debug, PartialEq, Clone, Copy, Eq)]
+pub struct ExportedFunc<'a> {
+    pub name: &'a str,
+    pub index: usize,
+    pub ty: Option<&'a FuncType>,
+}
+
+impl Module {
+    /// The type of the function at `index`.
+    pub fn func_type(&self, index: usize) -> Option<&FuncType> {
+        let func = self.funcs.get(index)?;
+        self.types.get(func.f_type as usize)
+    }
+
+    /// Every function export, in the order the module lists them.
+    pub fn exported_funcs(&self) -> Vec<ExportedFunc<'_>> {
+        self.exports
+            .iter()
+            .map(|export| {
+                let EDesc::FuncExport(index) = export.e_desc;
+                ExportedFunc {
+                    name: &export.name,
+                    index,
+                    ty: self.func_type(index),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Writes `t` the way the text format spells it, e.g.
+/// `(param i32 i32) (result i32)`.
+pub fn signature(t: &FuncType) -> String {
+    let list = |kind: &str, types: &StackType| {
+        let types: Vec<String> = types.iter().map(|t| format!(" {}", t)).collect();
+        format!("({}{})", kind, types.concat())
+    };
+    match t {
+        (params, results) if results.is_empty() => list("param", params),
+        (params, results) => format!("{} {}", list("param", params), list("result", results)),
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueType::I32 => write!(f, "i32"),
+            ValueType::I64 => write!(f, "i64"),
+        }
+    }
+}
+
+impl Instr {
+    /// The instruction's name in the text format, without immediates.
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instr::LocalGet(_) => "local.get",
+            Instr::I32Add => "i32.add",
+        }
+    }
+}
+
+impl fmt::Display for Instr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instr::LocalGet(i) => write!(f, "{} {}", self.mnemonic(), i),
+            Instr::I32Add => write!(f, "{}", self.mnemonic()),
+        }
+    }
+}
+
+/// Writes the module in the text format. The binary format keeps no names,
+/// so each function is named `$f` followed by its index.
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(module")?;
+        for (index, func) in self.funcs.iter().enumerate() {
+            write!(f, "\n  (func $f{}", index)?;
+            if let Some((params, results)) = self.types.get(func.f_type as usize) {
+                params
+                    .iter()
+                    .try_for_each(|t| write!(f, " (param {})", t))?;
+                results
+                    .iter()
+                    .try_for_each(|t| write!(f, " (result {})", t))?;
+            }
+            func.locals
+                .iter()
+                .try_for_each(|t| write!(f, " (local {})", t))?;
+            func.body
+                .iter()
+                .try_for_each(|instr| write!(f, "\n    {}", instr))?;
+            write!(f, ")")?;
+        }
+        for export in &self.exports {
+            let EDesc::FuncExport(index) = export.e_desc;
+            write!(f, "\n  (export \"{}\" (func {}))", export.name, index)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Generates modules the compiler can encode and the decoder read back:
+/// counts, indices and sizes stay within one byte.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Module {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        fn value_types(u: &mut arbitrary::Unstructured) -> arbitrary::Result<StackType> {
+            let len = u.int_in_range(0..=4)?;
+            (0..len)
+                .map(|_| Ok(*u.choose(&[ValueType::I32, ValueType::I64])?))
+                .collect()
+        }
+
+        let types: Vec<Type> = (0..u.int_in_range(0..=4)?)
+            .map(|_| Ok((value_types(u)?, value_types(u)?)))
+            .collect::<arbitrary::Result<_>>()?;
+
+        let max_funcs = if types.is_empty() { 0 } else { 3 };
+        let funcs = (0..u.int_in_range(0..=max_funcs)?)
+            .map(|_| {
+                let body = (0..u.int_in_range(0..=16)?)
+                    .map(|_| match u.arbitrary()? {
+                        true => Ok(Instr::LocalGet(u.int_in_range(0..=127)?)),
+                        false => Ok(Instr::I32Add),
+                    })
+                    .collect::<arbitrary::Result<_>>()?;
+                Ok(Func {
+                    f_type: u.int_in_range(0..=types.len() as i32 - 1)?,
+                    locals: value_types(u)?,
+                    body,
+                })
+            })
+            .collect::<arbitrary::Result<_>>()?;
+
+        let exports = (0..u.int_in_range(0..=4)?)
+            .map(|_| {
+                let name = (0..u.int_in_range(1..=8)?)
+                    .map(|_| Ok(*u.choose(b"abcdefghijklmnopqrstuvwxyz_")? as char))
+                    .collect::<arbitrary::Result<_>>()?;
+                Ok(Export {
+                    name,
+                    e_desc: EDesc::FuncExport(u.int_in_range(0..=127)?),
+                })
+            })
+            .collect::<arbitrary::Result<_>>()?;
+
+        Ok(Module {
+            types,
+            funcs,
+            exports,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_matches_text_format() {
+        let add = (vec![ValueType::I32, ValueType::I64], vec![ValueType::I32]);
+
+        assert_eq!("(param i32 i64) (result i32)", signature(&add));
+        assert_eq!("(param)", signature(&(vec![], vec![])));
+    }
+
+    #[test]
+    fn exported_funcs_resolve_types() {
+        let id = (vec![ValueType::I32], vec![ValueType::I32]);
+        let export = |name: &str, index| Export {
+            name: name.to_string(),
+            e_desc: EDesc::FuncExport(index),
+        };
+        let module = Module {
+            types: vec![id.clone()],
+            funcs: vec![Func {
+                f_type: 0,
+                locals: vec![],
+                body: vec![Instr::LocalGet(0)],
+            }],
+            exports: vec![export("id", 0), export("missing", 1)],
+        };
+
+        assert_eq!(
+            vec![
+                ExportedFunc {
+                    name: "id",
+                    index: 0,
+                    ty: Some(&id),
+                },
+                ExportedFunc {
+                    name: "missing",
+                    index: 1,
+                    ty: None,
+                },
+            ],
+            module.exported_funcs()
+        );
+    }
+
+    #[test]
+    fn module_prints_in_text_format() {
+        let module = Module {
+            types: vec![(vec![ValueType::I32, ValueType::I32], vec![ValueType::I32])],
+            funcs: vec![Func {
+                f_type: 0,
+                locals: vec![ValueType::I64],
+                body: vec![Instr::LocalGet(0), Instr::LocalGet(1), Instr::I32Add],
+            }],
+            exports: vec![Export {
+                name: "add".to_string(),
+                e_desc: EDesc::FuncExport(0),
+            }],
+        };
+
+        assert_eq!(
+            "(module\n  \
+             (func $f0 (param i32) (param i32) (result i32) (local i64)\n    \
+             local.get 0\n    local.get 1\n    i32.add)\n  \
+             (export \"add\" (func 0)))",
+            module.to_string()
+        );
+        assert_eq!(
+            "(module)",
+            Module {
+                types: vec![],
+                funcs: vec![],
+                exports: vec![],
+            }
+            .to_string()
+        );
+    }
+
+    #[cfg(all(feature = "arbitrary", feature = "compiler"))]
+    #[test]
+    fn arbitrary_modules_round_trip_through_binary() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        for seed in 0..200u64 {
+            // A linear congruential generator is random enough to steer
+            // `Unstructured`, and keeps failures reproducible by seed.
+            let mut state = seed;
+            let data: Vec<u8> = (0..512)
+                .map(|_| {
+                    state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+                    (state >> 56) as u8
+                })
+                .collect();
+            let module = Module::arbitrary(&mut Unstructured::new(&data)).unwrap();
+            let binary = crate::compile(&module);
+
+            let (decoded, _) = crate::runtime::disassemble(binary).unwrap();
+            assert_eq!(module, decoded, "seed {}", seed);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn module_round_trips_through_json() {
+        let module = Module {
+            types: vec![(vec![ValueType::I32], vec![ValueType::I32])],
+            funcs: vec![Func {
+                f_type: 0,
+                locals: vec![],
+                body: vec![Instr::LocalGet(0)],
+            }],
+            exports: vec![Export {
+                name: "id".to_string(),
+                e_desc: EDesc::FuncExport(0),
+            }],
+        };
+
+        let json = serde_json::to_string(&module).unwrap();
+
+        assert_eq!(module, serde_json::from_str(&json).unwrap());
+    }
+}
@@ -0,0 +1,105 @@
+//! Compiling modules from a build script, so an application can embed
+//! them without shipping the compiler:
+//!
+//! ```ignore
+//! // build.rs
+//! fn main() {
+//!     zod::build::compile("src/add.zod").unwrap();
+//! }
+//!
+//! // src/main.rs
+//! static ADD: &[u8] = zod::include_zod!("add");
+//! ```
+
+use crate::parser::ParseError;
+use crate::runtime::{self, ValidationError};
+use crate::{compiler, parser};
+use std::fmt;
+use std::fs::{read_to_string, write};
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum BuildError {
+    NoOutDir,
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, ParseError),
+    Invalid(PathBuf, Vec<ValidationError>),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::NoOutDir => write!(f, "OUT_DIR is not set; call this from a build script"),
+            BuildError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            BuildError::Parse(path, err) => write!(f, "{}: {}", path.display(), err),
+            BuildError::Invalid(path, errors) => {
+                write!(f, "{}: invalid module", path.display())?;
+                errors.iter().try_for_each(|e| write!(f, "\n  {}", e))
+            }
+        }
+    }
+}
+
+/// Each message includes the error it wraps, so there is no separate
+/// source to report.
+impl std::error::Error for BuildError {}
+
+/// Compiles `source` to `$OUT_DIR/<name>.bin`, where `include_zod!` finds
+/// it, and has Cargo rerun the build script when the source changes.
+pub fn compile(source: impl AsRef<Path>) -> Result<PathBuf, BuildError> {
+    let out_dir = std::env::var_os("OUT_DIR").ok_or(BuildError::NoOutDir)?;
+    let source = source.as_ref();
+    println!("cargo:rerun-if-changed={}", source.display());
+    compile_to(source, Path::new(&out_dir))
+}
+
+/// Compiles and validates `source`, writing the module to `out_dir` under
+/// the source's file name with a `.bin` extension.
+pub fn compile_to(source: &Path, out_dir: &Path) -> Result<PathBuf, BuildError> {
+    let text = read_to_string(source).map_err(|e| BuildError::Io(source.into(), e))?;
+    let ast = parser::parse(&text).map_err(|e| BuildError::Parse(source.into(), e))?;
+    let binary = compiler::compile(&ast);
+    runtime::validate(binary.clone()).map_err(|e| BuildError::Invalid(source.into(), e))?;
+
+    let name = source.file_stem().unwrap_or(source.as_os_str());
+    let output = out_dir.join(name).with_extension("bin");
+    write(&output, binary).map_err(|e| BuildError::Io(output.clone(), e))?;
+    Ok(output)
+}
+
+/// Embeds a module compiled by `build::compile` as a `&'static [u8]`, by
+/// the source's file name without extension.
+#[macro_export]
+macro_rules! include_zod {
+    ($name:literal) => {
+        include_bytes!(concat!(env!("OUT_DIR"), "/", $name, ".bin"))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compile_to_writes_a_valid_module() {
+        let out_dir = std::env::temp_dir().join(format!("zod-out-{}", std::process::id()));
+        std::fs::create_dir_all(&out_dir).unwrap();
+        let output = compile_to(Path::new("main.zod"), &out_dir).unwrap();
+        let binary = std::fs::read(&output).unwrap();
+        std::fs::remove_dir_all(&out_dir).unwrap();
+
+        assert_eq!(out_dir.join("main.bin"), output);
+        assert_eq!(
+            Ok(3),
+            runtime::invoke_function(binary, "add", &[1, 2], Default::default())
+        );
+    }
+
+    #[test]
+    fn compile_to_reports_the_source() {
+        let err = compile_to(Path::new("missing.zod"), &std::env::temp_dir()).unwrap_err();
+
+        assert!(err.to_string().starts_with("missing.zod: "));
+    }
+}
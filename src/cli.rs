@@ -0,0 +1,696 @@
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use log::LevelFilter;
+use serde_json::json;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+use zod::parser::ParseError;
+use zod::runtime::{Change, RuntimeError, ValidationError, Value};
+
+/// A compiler and runtime for the Zod programming language.
+#[derive(Parser, Debug)]
+#[command(name = "zod", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+    /// Log more about what is going on; repeat for more detail
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+    /// Log nothing, not even warnings
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+}
+
+impl Cli {
+    /// The most detailed level to log at: warnings by default, `-v` for
+    /// info, `-vv` for decoding and instantiation steps, `-vvv` for all.
+    pub fn log_level(&self) -> LevelFilter {
+        match (self.quiet, self.verbose) {
+            (true, _) => LevelFilter::Off,
+            (false, 0) => LevelFilter::Warn,
+            (false, 1) => LevelFilter::Info,
+            (false, 2) => LevelFilter::Debug,
+            (false, _) => LevelFilter::Trace,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Compile .zod files to binary modules
+    Compile {
+        /// The .zod source files, directories or glob patterns such as 'src/**/*.zod', or -
+        /// for stdin
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Where to write the module, or - for stdout [default: <name>.bin in the current
+        /// directory, or stdout when reading stdin]
+        #[arg(short, long, conflicts_with = "out_dir")]
+        output: Option<PathBuf>,
+        /// Write each module under this directory, mirroring the sources' directories, and
+        /// skip those that are newer than their source
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+        /// Overwrite the output file if it exists, or rebuild up-to-date modules
+        #[arg(long)]
+        force: bool,
+        /// Sign each module with this Ed25519 private key, a PKCS#8 PEM file
+        #[arg(long, value_name = "KEY")]
+        sign: Option<PathBuf>,
+    },
+    /// Invoke an exported function of a module, compiling it first if it is source text
+    Execute(Execute),
+    /// Compile a .zod file in memory and invoke one of its functions
+    Run(Run),
+    /// List the functions a module exports, with their signatures
+    Exports {
+        /// The module (.bin, .wasm) or source (.zod, .wat), or - for stdin
+        path: PathBuf,
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Work with core dumps written by `--coredump`
+    Coredump {
+        #[command(subcommand)]
+        command: Coredump,
+    },
+    /// Show the version, name, producers and custom sections a module declares
+    Info {
+        /// The module (.bin, .wasm) or source (.zod, .wat), or - for stdin
+        path: PathBuf,
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Start an interactive session for defining and calling functions
+    Repl {
+        /// A .zod source file to start from
+        path: Option<PathBuf>,
+    },
+    /// Recompile a source file whenever it changes, optionally invoking a function
+    Watch {
+        /// The .zod source file
+        path: PathBuf,
+        /// The exported function to invoke after each successful build
+        #[arg(long, value_name = "FUNC")]
+        invoke: Option<String>,
+        /// Arguments passed to the function, typed as for execute
+        #[arg(allow_negative_numbers = true, requires = "invoke")]
+        args: Vec<Value>,
+    },
+    /// Time repeated calls to an exported function
+    Bench {
+        /// The module (.bin, .wasm) or source (.zod, .wat), or - for stdin
+        path: PathBuf,
+        /// The exported function to call
+        func: String,
+        /// Arguments passed to the function, typed as for execute
+        #[arg(allow_negative_numbers = true)]
+        args: Vec<Value>,
+        /// Timed calls to make
+        #[arg(long, default_value_t = 10_000)]
+        iterations: usize,
+        /// Untimed calls to make first
+        #[arg(long, default_value_t = 1_000)]
+        warmup: usize,
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Step through a call to an exported function interactively
+    Debug {
+        /// The module (.bin, .wasm) or source (.zod, .wat), or - for stdin
+        path: PathBuf,
+        /// The exported function to call
+        func: String,
+        /// Arguments passed to the function, typed as for execute
+        #[arg(allow_negative_numbers = true)]
+        args: Vec<Value>,
+    },
+    /// Compare two modules section by section, down to single instructions
+    Diff {
+        /// The original module (.bin, .wasm) or source (.zod, .wat), or - for stdin
+        old: PathBuf,
+        /// The module to compare it with
+        new: PathBuf,
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Call an exported function in zod and in another runtime and compare
+    Difftest {
+        /// The module (.bin, .wasm) or source (.zod, .wat), or - for stdin
+        path: PathBuf,
+        /// The exported function to call
+        func: String,
+        /// Arguments passed to the function, typed as for execute
+        #[arg(allow_negative_numbers = true)]
+        args: Vec<Value>,
+        /// The reference runtime's wasmtime-compatible command
+        #[arg(long, default_value = "wasmtime")]
+        runtime: String,
+    },
+    /// Show which functions call which, or the control flow of one function
+    Graph {
+        /// The module (.bin, .wasm) or source (.zod, .wat), or - for stdin
+        path: PathBuf,
+        /// Write Graphviz DOT instead of a list
+        #[arg(long)]
+        dot: bool,
+        /// Graph the basic blocks of this function, by export name or index
+        #[arg(long, value_name = "FUNC", requires = "dot")]
+        cfg: Option<String>,
+    },
+    /// Check that this build compiles, decodes and runs a set of built-in modules correctly
+    Selftest,
+    /// Report section sizes, function sizes and instruction counts
+    Stats {
+        /// The module (.bin, .wasm) or source (.zod, .wat), or - for stdin
+        path: PathBuf,
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Check that a module, or the module compiled from source, is valid
+    Validate {
+        /// The module (.bin, .wasm) or source (.zod, .wat), or - for stdin
+        path: PathBuf,
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// A JSON document
+    Json,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Coredump {
+    /// Show the trap, and the locals and operand stack of each frame
+    Inspect {
+        /// The core dump file
+        path: PathBuf,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct Execute {
+    /// The module (.bin, .wasm) or source (.zod, .wat), or - for stdin
+    pub path: PathBuf,
+    #[command(flatten)]
+    pub invoke: Invoke,
+}
+
+#[derive(Args, Debug)]
+pub struct Run {
+    /// The .zod source file, or - for stdin
+    pub path: PathBuf,
+    #[command(flatten)]
+    pub invoke: Invoke,
+}
+
+#[derive(Args, Debug)]
+pub struct Invoke {
+    /// The exported function to invoke
+    #[arg(required_unless_present = "script")]
+    pub func: Option<String>,
+    /// Arguments passed to the function: i32 by default, or typed as in
+    /// i64:9999999999 or f64:3.5
+    #[arg(allow_negative_numbers = true)]
+    pub args: Vec<Value>,
+    /// Read the arguments from a JSON array of numbers and typed strings
+    #[arg(long, value_name = "JSON", conflicts_with = "args")]
+    pub args_file: Option<PathBuf>,
+    /// Make the calls listed in a file, one `func args...` per line, on one instance
+    #[arg(long, value_name = "CALLS", conflicts_with_all = ["func", "args", "args_file"])]
+    pub script: Option<PathBuf>,
+    /// Trace every executed instruction, or only those of the listed functions
+    #[arg(long, value_name = "FUNCS", num_args = 0.., value_delimiter = ',', require_equals = true)]
+    pub trace: Option<Vec<String>>,
+    /// Step through the function interactively
+    #[arg(long)]
+    pub debug: bool,
+    /// Print instruction coverage, and write an lcov tracefile if a path is given
+    #[arg(long, value_name = "LCOV", num_args = 0..=1, require_equals = true)]
+    pub coverage: Option<Option<PathBuf>>,
+    /// Print a profile, or write folded stacks to the given path instead
+    #[arg(long, value_name = "FOLDED", num_args = 0..=1, require_equals = true)]
+    pub profile: Option<Option<PathBuf>>,
+    /// Stop the run once it has taken longer than this, e.g. 2s or 500ms
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub timeout: Option<Duration>,
+    /// Keep decoded modules in this directory and reuse them while the module is unchanged
+    #[arg(long, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+    /// Write a core dump to this file if a call traps, for `coredump inspect`
+    #[arg(long, value_name = "FILE")]
+    pub coredump: Option<PathBuf>,
+    /// Refuse to run the module unless it is signed with this Ed25519 public key, a PEM file
+    #[arg(long, value_name = "KEY")]
+    pub verify: Option<PathBuf>,
+    /// Trap once a call has executed this many instructions
+    #[arg(long, value_name = "INSTRS")]
+    pub fuel: Option<u64>,
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+/// Parses a number of milliseconds (`500ms`), seconds (`2s`, or a bare
+/// `2`) or minutes (`1.5m`).
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (number, scale) = if let Some(ms) = s.strip_suffix("ms") {
+        (ms, 0.001)
+    } else if let Some(secs) = s.strip_suffix('s') {
+        (secs, 1.0)
+    } else if let Some(mins) = s.strip_suffix('m') {
+        (mins, 60.0)
+    } else {
+        (s, 1.0)
+    };
+    match number.parse::<f64>() {
+        Ok(n) if n.is_finite() && n >= 0.0 => {
+            Duration::try_from_secs_f64(n * scale).map_err(|e| e.to_string())
+        }
+        _ => Err(format!("invalid duration {}, expected e.g. 2s or 500ms", s)),
+    }
+}
+
+impl Command {
+    /// The format the command reports results and errors in.
+    pub fn format(&self) -> OutputFormat {
+        match self {
+            Command::Execute(Execute { invoke, .. }) | Command::Run(Run { invoke, .. }) => {
+                invoke.format
+            }
+            Command::Bench { format, .. }
+            | Command::Diff { format, .. }
+            | Command::Exports { format, .. }
+            | Command::Info { format, .. }
+            | Command::Stats { format, .. }
+            | Command::Validate { format, .. } => *format,
+            _ => OutputFormat::Text,
+        }
+    }
+}
+
+/// A failed command, reported on stderr before exiting.
+#[derive(Debug)]
+pub enum Error {
+    Io(PathBuf, io::Error),
+    OutputExists(PathBuf),
+    Parse(PathBuf, ParseError),
+    Runtime(RuntimeError),
+    Invalid(PathBuf, Vec<ValidationError>),
+    Args(PathBuf, String),
+    Timeout(Duration),
+    Divergence(String),
+    /// The reference runtime of `difftest` failed without trapping, for
+    /// example because it rejected the module or the arguments.
+    Reference(String),
+    /// The differences `diff` found, printed already in text format.
+    Differ(Vec<Change>),
+    SelfTest {
+        failed: usize,
+        total: usize,
+    },
+    UnknownFunc(String),
+    /// Some of the files of a build failed, each reported as it happened.
+    Build {
+        failed: Vec<Error>,
+        total: usize,
+    },
+}
+
+impl Error {
+    /// Traps exit with 1 and usage errors, reported by clap, with 2.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Runtime(RuntimeError::Trap(_)) => 1,
+            Error::Parse(..) => 3,
+            Error::Runtime(_)
+            | Error::Invalid(..)
+            | Error::Args(..)
+            | Error::UnknownFunc(_)
+            | Error::Reference(_) => 4,
+            Error::Io(..) | Error::OutputExists(_) => 5,
+            Error::Timeout(_) => 6,
+            Error::Divergence(_) | Error::Differ(_) | Error::SelfTest { .. } => 7,
+            Error::Build { failed, .. } => failed.first().map_or(1, Error::exit_code),
+        }
+    }
+}
+
+impl Error {
+    /// The error as a JSON object for `--format json`.
+    pub fn to_json(&self) -> serde_json::Value {
+        let error = match self {
+            Error::Runtime(RuntimeError::Trap(trap)) => {
+                let backtrace: Vec<_> = trap
+                    .backtrace
+                    .iter()
+                    .map(|f| json!({"func": f.func, "name": f.name, "offset": f.offset}))
+                    .collect();
+                json!({
+                    "kind": "trap",
+                    "message": trap.kind.to_string(),
+                    "backtrace": backtrace,
+                })
+            }
+            Error::Invalid(path, errors) => json!({
+                "kind": "invalid",
+                "path": path,
+                "errors": errors.iter().map(validation_json).collect::<Vec<_>>(),
+            }),
+            Error::Parse(path, err) => json!({
+                "kind": "syntax",
+                "path": path,
+                "message": err.to_string(),
+                "line": err.line,
+                "column": err.column,
+            }),
+            Error::Runtime(err) => json!({"kind": "runtime", "message": err.to_string()}),
+            Error::Args(path, message) => json!({"kind": "args", "path": path, "message": message}),
+            Error::Timeout(timeout) => json!({
+                "kind": "timeout",
+                "message": self.to_string().trim_start_matches("error: "),
+                "timeout_ms": timeout.as_millis() as u64,
+            }),
+            Error::Divergence(message) => json!({"kind": "divergence", "message": message}),
+            Error::Reference(message) => json!({"kind": "reference", "message": message}),
+            Error::Differ(changes) => json!({
+                "kind": "diff",
+                "message": self.to_string().trim_start_matches("error: "),
+                "changes": changes.iter().map(change_json).collect::<Vec<_>>(),
+            }),
+            Error::SelfTest { failed, total } => json!({
+                "kind": "selftest",
+                "message": self.to_string().trim_start_matches("error: "),
+                "failed": failed,
+                "total": total,
+            }),
+            Error::UnknownFunc(func) => json!({
+                "kind": "runtime",
+                "message": self.to_string().trim_start_matches("error: "),
+                "func": func,
+            }),
+            Error::Build { failed, total } => json!({
+                "kind": "build",
+                "total": total,
+                "failed": failed.iter().map(|e| e.to_json()["error"].clone()).collect::<Vec<_>>(),
+            }),
+            Error::Io(path, err) => json!({"kind": "io", "path": path, "message": err.to_string()}),
+            Error::OutputExists(path) => json!({
+                "kind": "io",
+                "path": path,
+                "message": "output file already exists",
+            }),
+        };
+        json!({ "error": error, "exit_code": self.exit_code() })
+    }
+}
+
+pub fn validation_json(error: &ValidationError) -> serde_json::Value {
+    json!({
+        "message": error.to_string(),
+        "func": error.func(),
+        "offset": error.offset(),
+    })
+}
+
+pub fn change_json(change: &Change) -> serde_json::Value {
+    match change {
+        Change::Added { item, new } => json!({"change": "added", "item": item, "new": new}),
+        Change::Removed { item, old } => json!({"change": "removed", "item": item, "old": old}),
+        Change::Changed { item, old, new } => {
+            json!({"change": "changed", "item": item, "old": old, "new": new})
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(path, err) => write!(f, "error: {}: {}", path.display(), err),
+            Error::OutputExists(path) => write!(
+                f,
+                "error: {} already exists, use --force to overwrite it",
+                path.display()
+            ),
+            Error::Parse(path, err) => write!(f, "error: {}: {}", path.display(), err),
+            Error::Runtime(RuntimeError::Trap(trap)) => write!(f, "{}", trap),
+            Error::Runtime(err) => write!(f, "error: {}", err),
+            Error::Args(path, message) => write!(f, "error: {}: {}", path.display(), message),
+            Error::Timeout(timeout) => write!(f, "error: execution timed out after {:?}", timeout),
+            Error::Divergence(message) => write!(f, "error: runtimes disagree: {}", message),
+            Error::Reference(message) => write!(f, "error: reference runtime failed: {}", message),
+            Error::Differ(changes) if changes.len() == 1 => {
+                write!(f, "error: modules differ in 1 place")
+            }
+            Error::Differ(changes) => {
+                write!(f, "error: modules differ in {} places", changes.len())
+            }
+            Error::SelfTest { failed, total } => {
+                write!(f, "error: {} of {} self-tests failed", failed, total)
+            }
+            Error::UnknownFunc(func) => write!(f, "error: no function {}", func),
+            Error::Build { failed, total } => write!(
+                f,
+                "error: {} of {} files failed to compile",
+                failed.len(),
+                total
+            ),
+            Error::Invalid(path, errors) => {
+                write!(f, "error: {} is invalid", path.display())?;
+                errors
+                    .iter()
+                    .try_for_each(|e| write!(f, "\n  {}: {}", path.display(), e))
+            }
+        }
+    }
+}
+
+impl From<RuntimeError> for Error {
+    fn from(err: RuntimeError) -> Self {
+        Error::Runtime(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn cli_definition_is_valid() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn execute_parses_flags_and_negative_args() {
+        let cli = Cli::try_parse_from([
+            "zod",
+            "execute",
+            "add.bin",
+            "add",
+            "1",
+            "-2",
+            "--trace=add,sub",
+            "--coverage",
+        ])
+        .unwrap();
+
+        let Command::Execute(execute) = cli.command else {
+            panic!("expected execute");
+        };
+        let invoke = execute.invoke;
+        assert_eq!(vec![Value::I32(1), Value::I32(-2)], invoke.args);
+        assert_eq!(
+            Some(vec!["add".to_string(), "sub".to_string()]),
+            invoke.trace
+        );
+        assert_eq!(Some(None), invoke.coverage);
+        assert_eq!(None, invoke.profile);
+        assert!(!invoke.debug);
+    }
+
+    #[test]
+    fn compile_takes_output_and_force() {
+        let cli = Cli::try_parse_from(["zod", "compile", "add.zod", "-o", "out/add.bin"]).unwrap();
+
+        let Command::Compile {
+            paths,
+            output,
+            force,
+            ..
+        } = cli.command
+        else {
+            panic!("expected compile");
+        };
+        assert_eq!(vec![PathBuf::from("add.zod")], paths);
+        assert_eq!(Some(PathBuf::from("out/add.bin")), output);
+        assert!(!force);
+    }
+
+    #[test]
+    fn compile_takes_several_paths_and_out_dir() {
+        let cli = Cli::try_parse_from(["zod", "compile", "a.zod", "src", "--out-dir", "build"]);
+        let conflict = Cli::try_parse_from([
+            "zod",
+            "compile",
+            "a.zod",
+            "-o",
+            "a.bin",
+            "--out-dir",
+            "build",
+        ]);
+
+        let Command::Compile { paths, out_dir, .. } = cli.unwrap().command else {
+            panic!("expected compile");
+        };
+        assert_eq!(vec![PathBuf::from("a.zod"), PathBuf::from("src")], paths);
+        assert_eq!(Some(PathBuf::from("build")), out_dir);
+        assert_eq!(
+            clap::error::ErrorKind::ArgumentConflict,
+            conflict.unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn watch_takes_invocation_after_path() {
+        let cli =
+            Cli::try_parse_from(["zod", "watch", "add.zod", "--invoke", "add", "1", "i64:-2"])
+                .unwrap();
+
+        let Command::Watch { invoke, args, .. } = cli.command else {
+            panic!("expected watch");
+        };
+        assert_eq!(Some("add".to_string()), invoke);
+        assert_eq!(vec![Value::I32(1), Value::I64(-2)], args);
+    }
+
+    #[test]
+    fn debug_takes_a_function_and_typed_args() {
+        let cli = Cli::try_parse_from(["zod", "debug", "add.bin", "add", "1", "i64:-2"]).unwrap();
+
+        let Command::Debug { path, func, args } = cli.command else {
+            panic!("expected debug");
+        };
+        assert_eq!(PathBuf::from("add.bin"), path);
+        assert_eq!("add", func);
+        assert_eq!(vec![Value::I32(1), Value::I64(-2)], args);
+    }
+
+    #[test]
+    fn run_takes_source_and_invocation() {
+        let cli = Cli::try_parse_from(["zod", "run", "add.zod", "add", "1", "2"]).unwrap();
+
+        let Command::Run(run) = cli.command else {
+            panic!("expected run");
+        };
+        assert_eq!(PathBuf::from("add.zod"), run.path);
+        assert_eq!(Some("add".to_string()), run.invoke.func);
+        assert_eq!(vec![Value::I32(1), Value::I32(2)], run.invoke.args);
+    }
+
+    #[test]
+    fn script_replaces_the_invocation() {
+        let cli = Cli::try_parse_from(["zod", "execute", "add.bin", "--script", "calls.txt"]);
+        let conflict =
+            Cli::try_parse_from(["zod", "execute", "add.bin", "add", "--script", "calls.txt"]);
+
+        assert!(cli.is_ok());
+        assert_eq!(
+            clap::error::ErrorKind::ArgumentConflict,
+            conflict.unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn verbosity_flags_set_log_level() {
+        let level = |args: &[&str]| {
+            let args = [&["zod", "validate", "add.bin"], args].concat();
+            Cli::try_parse_from(args).map(|cli| cli.log_level())
+        };
+
+        assert_eq!(LevelFilter::Warn, level(&[]).unwrap());
+        assert_eq!(LevelFilter::Info, level(&["-v"]).unwrap());
+        assert_eq!(LevelFilter::Debug, level(&["-vv"]).unwrap());
+        assert_eq!(LevelFilter::Trace, level(&["-vvvv"]).unwrap());
+        assert_eq!(LevelFilter::Off, level(&["-q"]).unwrap());
+        assert!(level(&["-q", "-v"]).is_err());
+    }
+
+    #[test]
+    fn missing_arguments_are_usage_errors() {
+        let err = Cli::try_parse_from(["zod", "execute", "add.bin"]).unwrap_err();
+
+        assert_eq!(clap::error::ErrorKind::MissingRequiredArgument, err.kind());
+    }
+
+    #[test]
+    fn errors_as_json() {
+        let err = Error::Parse(PathBuf::from("add.zod"), ParseError { line: 2, column: 3 });
+
+        assert_eq!(
+            json!({
+                "error": {
+                    "kind": "syntax",
+                    "path": "add.zod",
+                    "message": "syntax error at 2:3",
+                    "line": 2,
+                    "column": 3,
+                },
+                "exit_code": 3,
+            }),
+            err.to_json()
+        );
+    }
+
+    #[test]
+    fn diff_reports_changes_in_the_error() {
+        let err = Error::Differ(vec![Change::Removed {
+            item: "export \"add\"".to_string(),
+            old: "func[0]".to_string(),
+        }]);
+
+        assert_eq!("error: modules differ in 1 place", err.to_string());
+        assert_eq!(
+            json!({
+                "error": {
+                    "kind": "diff",
+                    "message": "modules differ in 1 place",
+                    "changes": [{"change": "removed", "item": "export \"add\"", "old": "func[0]"}],
+                },
+                "exit_code": 7,
+            }),
+            err.to_json()
+        );
+    }
+
+    #[test]
+    fn errors_have_distinct_exit_codes() {
+        let path = PathBuf::from("add.zod");
+        let errors = [
+            Error::Parse(path.clone(), ParseError { line: 1, column: 1 }),
+            Error::Runtime(RuntimeError::ExportNotFound("add".to_string())),
+            Error::Io(path, io::ErrorKind::NotFound.into()),
+            Error::Timeout(Duration::from_secs(2)),
+            Error::Divergence(String::new()),
+        ];
+
+        let codes: Vec<i32> = errors.iter().map(Error::exit_code).collect();
+        assert_eq!(vec![3, 4, 5, 6, 7], codes);
+    }
+
+    #[test]
+    fn durations_take_a_unit() {
+        assert_eq!(Ok(Duration::from_millis(500)), parse_duration("500ms"));
+        assert_eq!(Ok(Duration::from_secs(2)), parse_duration("2s"));
+        assert_eq!(Ok(Duration::from_secs(2)), parse_duration("2"));
+        assert_eq!(Ok(Duration::from_secs(90)), parse_duration("1.5m"));
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("1e30s").is_err());
+    }
+}
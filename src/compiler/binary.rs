@@ -1,10 +1,12 @@
 use crate::ast::{EDesc, Export, Func, Instr, Module, Type};
 use crate::compiler::leb128::from_u32;
 use crate::op_codes::*;
+use alloc::vec;
+use alloc::vec::Vec;
 
 fn encode_type_section(ast: &Module) -> Vec<u8> {
     fn encode_type(t: &Type) -> Vec<u8> {
-        vec![
+        [
             vec![control_flow::FUNC],
             vec![t.0.len() as u8],
             t.0.iter().map(val_type).collect::<Vec<u8>>(),
@@ -17,11 +19,11 @@ fn encode_type_section(ast: &Module) -> Vec<u8> {
     let body: Vec<u8> = ast
         .types
         .iter()
-        .map(|t| encode_type(t))
+        .map(encode_type)
         .collect::<Vec<Vec<u8>>>()
         .concat();
 
-    vec![
+    [
         vec![section::TYPE],
         from_u32((body.len() + 1) as u32),
         from_u32(ast.types.len() as u32),
@@ -39,7 +41,7 @@ fn encode_func_section(ast: &Module) -> Vec<u8> {
             .iter()
             .map(|f| f.f_type as u8)
             .collect::<Vec<u8>>();
-        vec![
+        [
             vec![section::FUNC],
             from_u32((body.len() + 1) as u32),
             from_u32(ast.funcs.len() as u32),
@@ -51,7 +53,7 @@ fn encode_func_section(ast: &Module) -> Vec<u8> {
 
 fn encode_export_section(ast: &Module) -> Vec<u8> {
     fn encode_export(export: &Export) -> Vec<u8> {
-        vec![
+        [
             from_u32(export.name.len() as u32),
             export.name.as_bytes().to_vec(),
             match export.e_desc {
@@ -73,7 +75,7 @@ fn encode_export_section(ast: &Module) -> Vec<u8> {
             .map(encode_export)
             .collect::<Vec<Vec<u8>>>()
             .concat();
-        vec![
+        [
             vec![section::EXPORT],
             from_u32((body.len() + 1) as u32),
             from_u32(ast.exports.len() as u32),
@@ -92,8 +94,9 @@ fn encode_code_section(ast: &Module) -> Vec<u8> {
             }
         }
 
-        let body = vec![
+        let body = [
             vec![func.locals.len() as u8], // local decl count
+            func.locals.iter().map(val_type).collect::<Vec<u8>>(),
             func.body
                 .iter()
                 .map(encode_instr)
@@ -103,13 +106,13 @@ fn encode_code_section(ast: &Module) -> Vec<u8> {
         ]
         .concat();
 
-        vec![from_u32(body.len() as u32), body].concat()
+        [from_u32(body.len() as u32), body].concat()
     }
 
     if ast.funcs.is_empty() {
         vec![]
     } else {
-        let body = vec![
+        let body = [
             vec![ast.funcs.len() as u8],
             ast.funcs
                 .iter()
@@ -118,20 +121,22 @@ fn encode_code_section(ast: &Module) -> Vec<u8> {
                 .concat(),
         ]
         .concat();
-        vec![vec![section::CODE], from_u32((body.len()) as u32), body].concat()
+        [vec![section::CODE], from_u32((body.len()) as u32), body].concat()
     }
 }
 
 pub fn compile(ast: &Module) -> Vec<u8> {
-    vec![
-        MAGIC,
-        VERSION,
-        &encode_type_section(ast),
-        &encode_func_section(ast),
-        &encode_export_section(ast),
-        &encode_code_section(ast),
-    ]
-    .concat()
+    let sections = vec![
+        ("type", encode_type_section(ast)),
+        ("function", encode_func_section(ast)),
+        ("export", encode_export_section(ast)),
+        ("code", encode_code_section(ast)),
+    ];
+    for (name, bytes) in &sections {
+        log::debug!("encoded {} section, {} bytes", name, bytes.len());
+    }
+    let sections: Vec<Vec<u8>> = sections.into_iter().map(|(_, bytes)| bytes).collect();
+    [MAGIC.to_vec(), VERSION.to_vec(), sections.concat()].concat()
 }
 
 #[cfg(test)]
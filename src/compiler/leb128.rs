@@ -1,3 +1,5 @@
+use alloc::vec::Vec;
+
 pub fn from_u32(value: u32) -> Vec<u8> {
     fn encode(i: u32, r: &[u8]) -> Vec<u8> {
         let b = i & 0x7fu32;
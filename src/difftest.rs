@@ -0,0 +1,159 @@
+use crate::cli::Error;
+use std::fmt;
+use std::fs::write;
+use std::path::PathBuf;
+use std::process::Command;
+use zod::runtime::{self, Config, Instance, Value};
+
+/// How a call ended.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Result(String),
+    Trap(String),
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Result(result) => write!(f, "returned {}", result),
+            Outcome::Trap(message) => write!(f, "trapped: {}", message),
+        }
+    }
+}
+
+/// Calls `func` in zod. Errors that are not traps, such as a missing
+/// export, are returned as errors since there is nothing to compare.
+pub fn zod(binary: Vec<u8>, func: &str, args: &[Value]) -> Result<Outcome, Error> {
+    match Instance::new(binary, Config::default())?.call(func, args) {
+        Ok(result) => Ok(Outcome::Result(number(result))),
+        Err(runtime::RuntimeError::Trap(trap)) => Ok(Outcome::Trap(trap.kind.to_string())),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Calls `func` with the `wasmtime` command line, which prints the result
+/// on its last line of output or exits with an error after a trap.
+pub fn wasmtime(
+    program: &str,
+    binary: &[u8],
+    func: &str,
+    args: &[Value],
+) -> Result<Outcome, Error> {
+    let module = std::env::temp_dir().join(format!("zod-difftest-{}.wasm", std::process::id()));
+    write(&module, binary).map_err(|e| Error::Io(module.clone(), e))?;
+    let output = Command::new(program)
+        .arg("run")
+        .arg("--invoke")
+        .arg(func)
+        .arg(&module)
+        .args(args.iter().copied().map(number))
+        .output();
+    let _ = std::fs::remove_file(&module);
+
+    let output = output.map_err(|e| Error::Io(PathBuf::from(program), e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    reference_outcome(output.status.success(), &stdout, &stderr)
+}
+
+/// Markers wasmtime puts in its error output when the call trapped, as
+/// opposed to failing to load the module or parse the arguments.
+const TRAP_MARKERS: &[&str] = &["wasm trap:", "error while executing"];
+
+fn reference_outcome(success: bool, stdout: &str, stderr: &str) -> Result<Outcome, Error> {
+    if success {
+        let result = stdout.lines().last().unwrap_or("").trim();
+        return Ok(Outcome::Result(result.to_string()));
+    }
+    let lines = || stderr.lines().map(str::trim).filter(|l| !l.is_empty());
+    if !TRAP_MARKERS.iter().any(|marker| stderr.contains(marker)) {
+        return Err(Error::Reference(lines().collect::<Vec<_>>().join(" ")));
+    }
+    let message = lines()
+        .find(|l| l.contains(TRAP_MARKERS[0]))
+        .or_else(|| lines().next())
+        .unwrap_or("");
+    Ok(Outcome::Trap(message.to_string()))
+}
+
+/// Describes how the outcomes differ, if they do. Trap messages differ
+/// between runtimes, so two traps count as the same outcome.
+pub fn compare(zod: &Outcome, reference: &Outcome) -> Option<String> {
+    match (zod, reference) {
+        (Outcome::Result(a), Outcome::Result(b)) if a == b => None,
+        (Outcome::Trap(_), Outcome::Trap(_)) => None,
+        _ => Some(format!("zod {}, reference {}", zod, reference)),
+    }
+}
+
+/// The value as a plain number, the way other runtimes take and print it.
+fn number(value: Value) -> String {
+    match value {
+        Value::I32(v) => v.to_string(),
+        Value::I64(v) => v.to_string(),
+        Value::F64(v) => v.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_must_match_and_traps_match_each_other() {
+        let three = Outcome::Result("3".to_string());
+        let trap = |m: &str| Outcome::Trap(m.to_string());
+
+        assert_eq!(None, compare(&three, &Outcome::Result("3".to_string())));
+        assert_eq!(
+            None,
+            compare(&trap("stack underflow"), &trap("unreachable"))
+        );
+        assert_eq!(
+            Some("zod returned 3, reference trapped: unreachable".to_string()),
+            compare(&three, &trap("unreachable"))
+        );
+    }
+
+    #[test]
+    fn reference_output_is_parsed() {
+        assert_eq!(
+            Outcome::Result("3".to_string()),
+            reference_outcome(true, "warning: experimental\n3\n", "").unwrap()
+        );
+        let trap = "Error: failed to run main module\n\nCaused by:\n    \
+                    0: failed to invoke `add`\n    \
+                    1: error while executing at wasm backtrace:\n    \
+                    2: wasm trap: wasm `unreachable` instruction executed\n";
+        assert_eq!(
+            Outcome::Trap("2: wasm trap: wasm `unreachable` instruction executed".to_string()),
+            reference_outcome(false, "", trap).unwrap()
+        );
+    }
+
+    #[test]
+    fn reference_failures_are_not_traps() {
+        let stderr = "Error: failed to run main module\n\nCaused by:\n    \
+                      0: failed to compile module\n";
+
+        match reference_outcome(false, "", stderr) {
+            Err(Error::Reference(message)) => assert_eq!(
+                "Error: failed to run main module Caused by: 0: failed to compile module",
+                message
+            ),
+            other => panic!("expected a reference error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn zod_reports_results() {
+        let binary = zod::compiler::compile(
+            &zod::parser::parse(&std::fs::read_to_string("main.zod").unwrap()).unwrap(),
+        );
+
+        assert_eq!(
+            Outcome::Result("-1".to_string()),
+            zod(binary, "add", &[Value::I32(1), Value::I32(-2)]).unwrap()
+        );
+    }
+}
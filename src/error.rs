@@ -0,0 +1,91 @@
+#[cfg(feature = "text")]
+use crate::parser::ParseError;
+use crate::runtime::{RuntimeError, ValidationError};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Any error the library returns, for callers that handle them alike.
+#[derive(Debug)]
+pub enum Error {
+    #[cfg(feature = "text")]
+    Parse(ParseError),
+    Invalid(Vec<ValidationError>),
+    Runtime(RuntimeError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "text")]
+            Error::Parse(err) => write!(f, "{}", err),
+            Error::Invalid(errors) => {
+                write!(f, "invalid module")?;
+                errors.iter().try_for_each(|e| write!(f, "\n  {}", e))
+            }
+            Error::Runtime(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// `Display` already prints the wrapped error, so its source is the
+/// wrapped error's source, not the error itself; reporters that walk the
+/// chain would print it twice otherwise.
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "text")]
+            Error::Parse(err) => core::error::Error::source(err),
+            Error::Invalid(_) => None,
+            Error::Runtime(err) => core::error::Error::source(err),
+        }
+    }
+}
+
+#[cfg(feature = "text")]
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        Error::Parse(err)
+    }
+}
+
+impl From<Vec<ValidationError>> for Error {
+    fn from(errors: Vec<ValidationError>) -> Self {
+        Error::Invalid(errors)
+    }
+}
+
+impl From<RuntimeError> for Error {
+    fn from(err: RuntimeError) -> Self {
+        Error::Runtime(err)
+    }
+}
+
+#[cfg(all(test, feature = "text", feature = "compiler"))]
+mod tests {
+    use super::*;
+    use core::error::Error as _;
+
+    fn run(source: &str) -> Result<i32, Error> {
+        let binary = crate::compile(&crate::parse(source)?);
+        crate::runtime::validate(binary.clone())?;
+        Ok(crate::Instance::new(binary, Default::default())?.invoke("f", &[])?)
+    }
+
+    #[test]
+    fn errors_convert_without_repeating_themselves() {
+        let parse = run("(module").unwrap_err();
+        let invalid = run("(module (func $f (result i32) i32.add))").unwrap_err();
+        let runtime = run("(module)").unwrap_err();
+
+        assert_eq!("syntax error at 1:8", parse.to_string());
+        assert!(matches!(parse, Error::Parse(_)));
+        assert!(parse.source().is_none());
+        assert!(invalid
+            .to_string()
+            .starts_with("invalid module\n  func[0] @ 0x"));
+        assert!(invalid.source().is_none());
+        assert_eq!("no export named \"f\"", runtime.to_string());
+        assert!(matches!(runtime, Error::Runtime(_)));
+        assert!(runtime.source().is_none());
+    }
+}
@@ -0,0 +1,315 @@
+//! The C API declared in `include/zod.h`. Objects returned by one function
+//! are owned by the caller until passed to the matching `_free` function.
+
+use crate::{Config, Instance};
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::ptr;
+use std::slice;
+
+/// An error message, as handed out through `zod_error` pointers.
+pub struct ZodError {
+    message: CString,
+}
+
+/// A module compiled to its binary form.
+#[cfg(all(feature = "text", feature = "compiler"))]
+pub struct ZodModule {
+    binary: Vec<u8>,
+}
+
+fn set_error(out: *mut *mut ZodError, message: String) {
+    if !out.is_null() {
+        // Messages come from Display impls, which have no interior nulls.
+        let message = CString::new(message).unwrap_or_default();
+        // SAFETY: the caller passes either null or a valid place to write.
+        unsafe { *out = Box::into_raw(Box::new(ZodError { message })) };
+    }
+}
+
+/// Parses and compiles `source`, a null-terminated string. Returns null
+/// and sets `*error` if it is not a valid module.
+///
+/// # Safety
+///
+/// `source` must be a valid null-terminated string, and `error` null or a
+/// valid pointer.
+#[cfg(all(feature = "text", feature = "compiler"))]
+#[no_mangle]
+pub unsafe extern "C" fn zod_compile(
+    source: *const c_char,
+    error: *mut *mut ZodError,
+) -> *mut ZodModule {
+    let source = match CStr::from_ptr(source).to_str() {
+        Ok(source) => source,
+        Err(err) => {
+            set_error(error, err.to_string());
+            return ptr::null_mut();
+        }
+    };
+    let compiled = crate::parse(source)
+        .map_err(crate::Error::from)
+        .and_then(|ast| {
+            let binary = crate::compile(&ast);
+            crate::runtime::validate(binary.clone())?;
+            Ok(binary)
+        });
+    match compiled {
+        Ok(binary) => Box::into_raw(Box::new(ZodModule { binary })),
+        Err(err) => {
+            set_error(error, err.to_string());
+            ptr::null_mut()
+        }
+    }
+}
+
+/// The binary form of `module`, valid until the module is freed. Its
+/// length is written to `*len`.
+///
+/// # Safety
+///
+/// `module` must come from `zod_compile` and `len` must be valid.
+#[cfg(all(feature = "text", feature = "compiler"))]
+#[no_mangle]
+pub unsafe extern "C" fn zod_module_binary(module: *const ZodModule, len: *mut usize) -> *const u8 {
+    let binary = &(*module).binary;
+    *len = binary.len();
+    binary.as_ptr()
+}
+
+/// # Safety
+///
+/// `module` must be null or come from `zod_compile`, and not be used again.
+#[cfg(all(feature = "text", feature = "compiler"))]
+#[no_mangle]
+pub unsafe extern "C" fn zod_module_free(module: *mut ZodModule) {
+    if !module.is_null() {
+        drop(Box::from_raw(module));
+    }
+}
+
+/// Instantiates the binary module of `len` bytes at `binary`. Returns null
+/// and sets `*error` if it cannot be decoded.
+///
+/// # Safety
+///
+/// `binary` must be null or point to `len` readable bytes, and `error` be
+/// null or a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn zod_instantiate(
+    binary: *const u8,
+    len: usize,
+    error: *mut *mut ZodError,
+) -> *mut Instance {
+    let binary = match len {
+        0 => Vec::new(),
+        _ if binary.is_null() => Vec::new(),
+        _ => slice::from_raw_parts(binary, len).to_vec(),
+    };
+    match Instance::new(binary, Config::default()) {
+        Ok(instance) => Box::into_raw(Box::new(instance)),
+        Err(err) => {
+            set_error(error, err.to_string());
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Invokes the export `func` with `nargs` i32 arguments and writes its
+/// result to `*result`. Returns 0 on success, or -1 after setting `*error`.
+///
+/// # Safety
+///
+/// `instance` must come from `zod_instantiate`, `func` be a valid
+/// null-terminated string, `args` point to `nargs` values, `result` be
+/// valid and `error` be null or a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn zod_invoke(
+    instance: *mut Instance,
+    func: *const c_char,
+    args: *const i32,
+    nargs: usize,
+    result: *mut i32,
+    error: *mut *mut ZodError,
+) -> i32 {
+    let args = match nargs {
+        0 => &[],
+        _ => slice::from_raw_parts(args, nargs),
+    };
+    let func = CStr::from_ptr(func).to_string_lossy();
+    match (*instance).invoke(&func, args) {
+        Ok(value) => {
+            *result = value;
+            0
+        }
+        Err(err) => {
+            set_error(error, err.to_string());
+            -1
+        }
+    }
+}
+
+/// # Safety
+///
+/// `instance` must be null or come from `zod_instantiate`, and not be used
+/// again.
+#[no_mangle]
+pub unsafe extern "C" fn zod_instance_free(instance: *mut Instance) {
+    if !instance.is_null() {
+        drop(Box::from_raw(instance));
+    }
+}
+
+/// The error's message, valid until the error is freed.
+///
+/// # Safety
+///
+/// `error` must come from one of the functions above.
+#[no_mangle]
+pub unsafe extern "C" fn zod_error_message(error: *const ZodError) -> *const c_char {
+    (*error).message.as_ptr()
+}
+
+/// # Safety
+///
+/// `error` must be null or come from one of the functions above, and not
+/// be used again.
+#[no_mangle]
+pub unsafe extern "C" fn zod_error_free(error: *mut ZodError) {
+    if !error.is_null() {
+        drop(Box::from_raw(error));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(all(feature = "text", feature = "compiler"))]
+    const ADD: &[u8] = b"(module
+        (func $add (param i32) (param i32) (result i32) local.get 0 local.get 1 i32.add)
+        (export \"add\" (func $add)))\0";
+
+    fn message(error: *mut ZodError) -> String {
+        unsafe {
+            let message = CStr::from_ptr(zod_error_message(error)).to_string_lossy();
+            let message = message.into_owned();
+            zod_error_free(error);
+            message
+        }
+    }
+
+    #[cfg(all(feature = "text", feature = "compiler"))]
+    #[test]
+    fn compile_instantiate_and_invoke() {
+        unsafe {
+            let mut error = ptr::null_mut();
+            let module = zod_compile(ADD.as_ptr() as *const c_char, &mut error);
+            let mut len = 0;
+            let binary = zod_module_binary(module, &mut len);
+            let instance = zod_instantiate(binary, len, &mut error);
+            zod_module_free(module);
+
+            let mut result = 0;
+            let add = b"add\0".as_ptr() as *const c_char;
+            let status = zod_invoke(instance, add, [1, 2].as_ptr(), 2, &mut result, &mut error);
+            assert_eq!((0, 3), (status, result));
+
+            let sub = b"sub\0".as_ptr() as *const c_char;
+            let status = zod_invoke(instance, sub, ptr::null(), 0, &mut result, &mut error);
+            assert_eq!(-1, status);
+            assert_eq!("no export named \"sub\"", message(error));
+            zod_instance_free(instance);
+        }
+    }
+
+    #[cfg(all(feature = "text", feature = "compiler"))]
+    #[test]
+    fn compile_errors_are_reported() {
+        unsafe {
+            let mut error = ptr::null_mut();
+            let module = zod_compile(b"(module\0".as_ptr() as *const c_char, &mut error);
+
+            assert!(module.is_null());
+            assert_eq!("syntax error at 1:8", message(error));
+        }
+    }
+
+    #[test]
+    fn instantiate_rejects_null_binaries() {
+        unsafe {
+            let mut error = ptr::null_mut();
+            let instance = zod_instantiate(ptr::null(), 8, &mut error);
+
+            assert!(instance.is_null());
+            assert_eq!("module is shorter than its header", message(error));
+        }
+    }
+
+    /// The C spelling of a Rust type used in the API.
+    fn c_type(rust: &str) -> String {
+        let pointer = |inner: String| {
+            if inner.ends_with('*') {
+                inner + "*"
+            } else {
+                inner + " *"
+            }
+        };
+        if let Some(inner) = rust.strip_prefix("*const ") {
+            return pointer(format!("const {}", c_type(inner)));
+        }
+        if let Some(inner) = rust.strip_prefix("*mut ") {
+            return pointer(c_type(inner));
+        }
+        let c = match rust {
+            "c_char" => "char",
+            "u8" => "uint8_t",
+            "i32" => "int32_t",
+            "usize" => "size_t",
+            "ZodError" => "zod_error",
+            "ZodModule" => "zod_module",
+            "Instance" => "zod_instance",
+            _ => panic!("no C type for {}", rust),
+        };
+        c.to_string()
+    }
+
+    /// `name` and `ty` as a C declaration, such as `const char *source`.
+    fn c_decl(ty: &str, name: &str) -> String {
+        let ty = c_type(ty);
+        if ty.ends_with('*') {
+            format!("{}{}", ty, name)
+        } else {
+            format!("{} {}", ty, name)
+        }
+    }
+
+    #[test]
+    fn header_matches_the_definitions() {
+        let words = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let header = words(include_str!("../include/zod.h"));
+        let source = include_str!("ffi.rs");
+        let prefix = "pub unsafe extern \"C\" fn ";
+
+        for definition in source.split(prefix).skip(1) {
+            let signature = words(&definition[..definition.find('{').unwrap()]);
+            let (name, rest) = signature.split_once('(').unwrap();
+            let (params, ret) = rest.split_once(')').unwrap();
+            let params: Vec<String> = params
+                .split(',')
+                .filter(|p| !p.trim().is_empty())
+                .map(|p| {
+                    let (name, ty) = p.split_once(':').unwrap();
+                    c_decl(ty.trim(), name.trim())
+                })
+                .collect();
+            let ret = match ret.trim().strip_prefix("-> ") {
+                Some(ty) => c_decl(ty, name),
+                None => format!("void {}", name),
+            };
+            let declaration = format!("{}({});", ret, params.join(", "));
+
+            assert!(header.contains(&declaration), "{}", declaration);
+        }
+    }
+}
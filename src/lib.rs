@@ -0,0 +1,65 @@
+//! A compiler and runtime for the Zod programming language, a subset of the
+//! WebAssembly text format.
+//!
+//! ```
+//! # #[cfg(all(feature = "text", feature = "compiler"))]
+//! # fn main() -> Result<(), zod::Error> {
+//! let source = r#"(module
+//!     (func $add (param i32) (param i32) (result i32)
+//!         local.get 0 local.get 1 i32.add)
+//!     (export "add" (func $add)))"#;
+//!
+//! let module = zod::parse(source)?;
+//! let binary = zod::compile(&module);
+//! zod::runtime::validate(binary.clone())?;
+//! let mut instance = zod::Instance::new(binary, zod::Config::default())?;
+//!
+//! assert_eq!(3, instance.invoke("add", &[1, 2])?);
+//! # Ok(())
+//! # }
+//! # #[cfg(not(all(feature = "text", feature = "compiler")))]
+//! # fn main() {}
+//! ```
+//!
+//! Only the runtime is always built. The `text` feature adds the parser
+//! and `compiler` the compiler; both are on by default as part of `cli`,
+//! which builds the command line tool. Embedders that only run prebuilt
+//! modules can turn off default features.
+//!
+//! Each stage has its own error type, and all of them convert into
+//! [`Error`].
+//!
+//! Without the `std` feature, which every other feature but `compiler` and
+//! `serde` turns on, the crate is `no_std` and needs only `alloc`: modules
+//! can be decoded, validated and run, but the tools that print, read
+//! files or time calls (tracing, debugging, profiling, benchmarking, the
+//! module cache and the instance pool) are left out.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+pub mod ast;
+#[cfg(all(feature = "text", feature = "compiler"))]
+pub mod build;
+#[cfg(feature = "compiler")]
+pub mod compiler;
+mod error;
+#[cfg(feature = "capi")]
+pub mod ffi;
+mod op_codes;
+#[cfg(feature = "text")]
+pub mod parser;
+#[cfg(all(feature = "text", feature = "compiler"))]
+pub mod round_trip;
+pub mod runtime;
+
+pub use crate::ast::Module;
+#[cfg(feature = "compiler")]
+pub use crate::compiler::compile;
+pub use crate::error::Error;
+#[cfg(feature = "text")]
+pub use crate::parser::{parse, ParseError};
+pub use crate::runtime::{
+    Config, Frame, Instance, RuntimeError, Trap, TrapKind, ValidationError, Value,
+};
@@ -0,0 +1,28 @@
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Writes log records to stderr as `level: message`.
+struct Stderr;
+
+impl Log for Stderr {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let level = record.level().as_str().to_lowercase();
+            eprintln!("{}: {}", level, record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: Stderr = Stderr;
+
+/// Sends log records up to `level` to stderr.
+pub fn init(level: LevelFilter) {
+    // Only fails if a logger is already set, which then keeps working.
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(level);
+}
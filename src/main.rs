@@ -1,55 +1,919 @@
-use std::fs::{read_to_string, File};
-use std::io::{Read, Write};
+use clap::Parser;
+use std::cell::RefCell;
+use std::fs::{create_dir_all, read, read_to_string, write, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
-mod ast;
-mod compiler;
-mod op_codes;
-mod parser;
-mod runtime;
+mod cli;
+mod difftest;
+mod logger;
+mod repl;
+mod selftest;
+
+use cli::{Cli, Command, Coredump, Error, Invoke, OutputFormat};
+use runtime::interrupt::InterruptHandle;
+use runtime::signature::{DecodePrivateKey, DecodePublicKey, SigningKey, VerifyingKey};
+use runtime::Value;
+use serde_json::json;
+use zod::{ast, compiler, parser, runtime};
 
 fn main() {
-    let args = std::env::args().collect::<Vec<String>>();
-    let flag = args[1].clone();
-    let path = std::path::Path::new(args[2].as_str());
-
-    match flag.as_str() {
-        "--compile" => {
-            // Parse the "add.zod" file with the binary text representation.
-            let zod = read_to_string(path).expect("Failed to read zod file.");
-            let ast = parser::parse(&zod);
-
-            // Compile the binary text representation to binary binary code and save the
-            // compiled module in the file "add.binary"
-            let binary = compiler::compile(&ast);
-            let file_name = format!(
-                "{}.bin",
-                path.file_name()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .split(".")
-                    .collect::<Vec<&str>>()[0]
-            );
-            let mut file = File::create(&file_name).expect("Failed to create binary file.");
-            file.write_all(&binary)
-                .expect("Failed to write binary file.");
-            println!(">> {}", file_name);
-        }
-        "--execute" => {
-            // Determine the function name to run and its arguments
-            let func = args[3].clone();
-            let func_args = &args[4..]
-                .into_iter()
-                .map(|i| i.parse().unwrap())
-                .collect::<Vec<i32>>();
-
-            // Read the compiled binary module "add.binary" and execute the function "add" from it.
-            let mut binary = vec![];
-            File::open(path).unwrap().read_to_end(&mut binary).unwrap();
-            let result = runtime::invoke_function(binary, &func, func_args).unwrap();
-
-            println!(">> {}", result);
-        }
-        _ => {}
+    let cli = Cli::parse();
+    logger::init(cli.log_level());
+    let command = cli.command;
+    let format = command.format();
+    let result = match command {
+        Command::Compile {
+            paths,
+            output,
+            out_dir,
+            force,
+            sign,
+        } => sign
+            .map(|path| read_key(&path, SigningKey::from_pkcs8_pem))
+            .transpose()
+            .and_then(|key| match (paths.as_slice(), out_dir) {
+                ([path], None) if !path.is_dir() && !is_pattern(path) => {
+                    compile(path, output, force, key.as_ref())
+                }
+                (_, out_dir) => {
+                    let out_dir = out_dir.unwrap_or_else(|| ".".into());
+                    build(&paths, &out_dir, force, key.as_ref())
+                }
+            }),
+        Command::Execute(execute) => load_module(&execute.path)
+            .and_then(|binary| invoke(binary, &execute.path, execute.invoke)),
+        Command::Exports { path, format } => exports(&path, format),
+        Command::Info { path, format } => info(&path, format),
+        Command::Coredump {
+            command: Coredump::Inspect { path },
+        } => inspect_coredump(&path),
+        Command::Repl { path } => repl(path),
+        Command::Watch { path, invoke, args } => watch(&path, invoke.as_deref(), &args),
+        Command::Bench {
+            path,
+            func,
+            args,
+            iterations,
+            warmup,
+            format,
+        } => bench(&path, &func, &args, warmup, iterations, format),
+        Command::Debug { path, func, args } => debug(&path, &func, &args),
+        Command::Diff { old, new, format } => diff(&old, &new, format),
+        Command::Difftest {
+            path,
+            func,
+            args,
+            runtime,
+        } => difftest(&path, &func, &args, &runtime),
+        Command::Graph { path, dot, cfg } => graph(&path, dot, cfg),
+        Command::Selftest => selftest(),
+        Command::Stats { path, format } => stats(&path, format),
+        Command::Validate { path, format } => validate(&path, format),
+        Command::Run(run) => {
+            compile_source(&run.path).and_then(|binary| invoke(binary, &run.path, run.invoke))
+        }
+    };
+
+    if let Err(err) = result {
+        match format {
+            OutputFormat::Text => eprintln!("{}", err),
+            OutputFormat::Json => println!("{:#}", err.to_json()),
+        }
+        std::process::exit(err.exit_code());
+    }
+}
+
+/// Whether `path` stands for stdin or stdout.
+fn is_std(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// Reads the file at `path`, or stdin if `path` is `-`.
+fn read_input(path: &Path) -> Result<Vec<u8>, Error> {
+    let mut bytes = vec![];
+    let result = if is_std(path) {
+        io::stdin().read_to_end(&mut bytes)
+    } else {
+        std::fs::File::open(path).and_then(|mut f| f.read_to_end(&mut bytes))
+    };
+    result.map_err(|e| Error::Io(path.into(), e))?;
+    Ok(bytes)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Format {
+    Binary,
+    Text,
+}
+
+/// Tells a compiled module from source text by the file extension, or by
+/// the magic bytes when the extension is unknown.
+fn detect_format(path: &Path, bytes: &[u8]) -> Format {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("bin") | Some("wasm") => Format::Binary,
+        Some("zod") | Some("wat") => Format::Text,
+        _ if bytes.starts_with(b"\0asm") => Format::Binary,
+        _ => Format::Text,
+    }
+}
+
+/// Reads a compiled module, compiling it first if `path` holds source text.
+fn load_module(path: &Path) -> Result<Vec<u8>, Error> {
+    let bytes = read_input(path)?;
+    let format = detect_format(path, &bytes);
+    log::info!("loading {} as {:?}", path.display(), format);
+    match format {
+        Format::Binary => Ok(bytes),
+        Format::Text => compile_text(path, bytes),
+    }
+}
+
+/// Parses the "add.zod" file with the binary text representation and
+/// compiles it to binary code.
+fn compile_source(path: &Path) -> Result<Vec<u8>, Error> {
+    compile_text(path, read_input(path)?)
+}
+
+fn compile_text(path: &Path, bytes: Vec<u8>) -> Result<Vec<u8>, Error> {
+    let zod = String::from_utf8(bytes)
+        .map_err(|e| Error::Io(path.into(), io::Error::new(io::ErrorKind::InvalidData, e)))?;
+    let ast = parser::parse(&zod).map_err(|e| Error::Parse(path.into(), e))?;
+    let binary = compiler::compile(&ast);
+    log::info!("compiled {} to {} bytes", path.display(), binary.len());
+    Ok(binary)
+}
+
+/// Reads a PEM key file with `parse`.
+fn read_key<K, E: std::fmt::Display>(
+    path: &Path,
+    parse: impl Fn(&str) -> Result<K, E>,
+) -> Result<K, Error> {
+    let pem = read_to_string(path).map_err(|e| Error::Io(path.into(), e))?;
+    parse(&pem).map_err(|e| Error::Args(path.into(), format!("invalid key: {}", e)))
+}
+
+/// Compiles the source at `path`, signing the module if given a key.
+fn compile_signed(path: &Path, key: Option<&SigningKey>) -> Result<Vec<u8>, Error> {
+    let binary = compile_source(path)?;
+    Ok(match key {
+        Some(key) => runtime::signature::sign(&binary, key),
+        None => binary,
+    })
+}
+
+fn compile(
+    path: &Path,
+    output: Option<PathBuf>,
+    force: bool,
+    key: Option<&SigningKey>,
+) -> Result<(), Error> {
+    // Save the compiled module in the file "add.bin" unless told otherwise.
+    // Source read from stdin is written to stdout by default.
+    let binary = compile_signed(path, key)?;
+    let output = match output {
+        Some(output) => output,
+        None if is_std(path) => PathBuf::from("-"),
+        None => Path::new(path.file_stem().unwrap_or(path.as_os_str())).with_extension("bin"),
+    };
+    write_output(&output, &binary, force)?;
+    if !is_std(&output) {
+        println!(">> {}", output.display());
+    }
+    Ok(())
+}
+
+fn is_pattern(path: &Path) -> bool {
+    path.to_string_lossy().contains(['*', '?', '['])
+}
+
+/// Finds the source files to build, each with the path to mirror under the
+/// output directory: relative to the directory or to the part of a glob
+/// pattern before its first wildcard, or just the file name.
+fn find_sources(paths: &[PathBuf]) -> Result<Vec<(PathBuf, PathBuf)>, Error> {
+    let mut sources = vec![];
+    for path in paths {
+        if is_pattern(path) {
+            let base: PathBuf = path
+                .components()
+                .take_while(|c| !is_pattern(Path::new(c.as_os_str())))
+                .collect();
+            let pattern = path.to_string_lossy();
+            let invalid = |e: glob::PatternError| {
+                Error::Io(path.clone(), io::Error::new(io::ErrorKind::InvalidInput, e))
+            };
+            for entry in glob::glob(&pattern).map_err(invalid)? {
+                let source = entry.map_err(|e| Error::Io(e.path().into(), e.into()))?;
+                if source.is_file() {
+                    let relative = source.strip_prefix(&base).unwrap_or(&source).to_path_buf();
+                    sources.push((source, relative));
+                }
+            }
+        } else if path.is_dir() {
+            walk(path, path, &mut sources)?;
+        } else {
+            let name = path.file_name().map_or_else(|| path.clone(), PathBuf::from);
+            sources.push((path.clone(), name));
+        }
+    }
+    Ok(sources)
+}
+
+/// Collects the source files under `dir`, recursively.
+fn walk(root: &Path, dir: &Path, sources: &mut Vec<(PathBuf, PathBuf)>) -> Result<(), Error> {
+    let io_error = |e| Error::Io(dir.into(), e);
+    let mut entries = std::fs::read_dir(dir)
+        .and_then(|entries| {
+            entries
+                .map(|e| e.map(|e| e.path()))
+                .collect::<io::Result<Vec<_>>>()
+        })
+        .map_err(io_error)?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            walk(root, &path, sources)?;
+        } else if matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("zod" | "wat")
+        ) {
+            let relative = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+            sources.push((path, relative));
+        }
+    }
+    Ok(())
+}
+
+/// Whether `output` was written after `source` last changed.
+fn up_to_date(source: &Path, output: &Path) -> bool {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    match (modified(source), modified(output)) {
+        (Some(source), Some(output)) => output >= source,
+        _ => false,
+    }
+}
+
+/// Compiles every source found in `paths` to a module under `out_dir`,
+/// carrying on past failures and summarizing at the end.
+fn build(
+    paths: &[PathBuf],
+    out_dir: &Path,
+    force: bool,
+    key: Option<&SigningKey>,
+) -> Result<(), Error> {
+    let sources = find_sources(paths)?;
+    let (mut compiled, mut skipped, mut failed) = (0, 0, vec![]);
+    for (source, relative) in &sources {
+        let output = out_dir.join(relative).with_extension("bin");
+        if !force && up_to_date(source, &output) {
+            log::info!("{} is up to date", output.display());
+            skipped += 1;
+            continue;
+        }
+        match compile_signed(source, key).and_then(|binary| write_output(&output, &binary, true)) {
+            Ok(()) => {
+                println!(">> {}", output.display());
+                compiled += 1;
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                failed.push(err);
+            }
+        }
+    }
+
+    eprintln!(
+        "compiled {}, {} up to date, {} failed",
+        compiled,
+        skipped,
+        failed.len()
+    );
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Build {
+            failed,
+            total: sources.len(),
+        })
+    }
+}
+
+/// Writes `bytes` to `path`, or stdout if `path` is `-`, creating missing
+/// parent directories. An existing file is only replaced when `force` is
+/// set.
+fn write_output(path: &Path, bytes: &[u8], force: bool) -> Result<(), Error> {
+    let io_error = |e: io::Error| match e.kind() {
+        io::ErrorKind::AlreadyExists => Error::OutputExists(path.into()),
+        _ => Error::Io(path.into(), e),
+    };
+
+    if is_std(path) {
+        let mut stdout = io::stdout().lock();
+        return stdout
+            .write_all(bytes)
+            .and_then(|_| stdout.flush())
+            .map_err(io_error);
+    }
+
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent).map_err(io_error)?;
+    }
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .create_new(!force)
+        .open(path)
+        .map_err(io_error)?;
+    file.write_all(bytes).map_err(io_error)
+}
+
+fn exports(path: &Path, format: OutputFormat) -> Result<(), Error> {
+    let (module, _) = runtime::disassemble(load_module(path)?)?;
+    let mut exports = vec![];
+    for export in &module.exports {
+        let ast::EDesc::FuncExport(index) = export.e_desc;
+        let f_type = module
+            .funcs
+            .get(index)
+            .and_then(|f| module.types.get(f.f_type as usize));
+        let text = match f_type {
+            Some(f_type) => format!("{}: func {}", export.name, ast::signature(f_type)),
+            // Left for validate to explain.
+            None => format!("{}: func[{}] with unknown type", export.name, index),
+        };
+        let types = |types: &ast::StackType| -> Vec<String> {
+            types.iter().map(ToString::to_string).collect()
+        };
+        let json = json!({
+            "name": export.name,
+            "kind": "func",
+            "index": index,
+            "params": f_type.map(|t| types(&t.0)),
+            "results": f_type.map(|t| types(&t.1)),
+        });
+        exports.push((text, json));
+    }
+
+    match format {
+        OutputFormat::Text => exports.iter().for_each(|(text, _)| println!("{}", text)),
+        OutputFormat::Json => {
+            let exports: Vec<_> = exports.into_iter().map(|(_, json)| json).collect();
+            println!("{:#}", json!(exports));
+        }
+    }
+    Ok(())
+}
+
+fn repl(path: Option<PathBuf>) -> Result<(), Error> {
+    let mut repl = repl::Repl::new();
+    if let Some(path) = path {
+        let loaded = repl.eval(&format!(":load {}", path.display()));
+        match loaded {
+            Ok(text) => println!("{}", text),
+            Err(err) => eprintln!("error: {}", err),
+        }
+    }
+    repl.run(&mut io::stdin().lock(), &mut io::stdout());
+    Ok(())
+}
+
+/// Rebuilds `path`, and invokes `func` if given, every time the file's
+/// modification time changes. Runs until interrupted.
+fn watch(path: &Path, func: Option<&str>, args: &[Value]) -> Result<(), Error> {
+    let mut last = None;
+    loop {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        if modified != last {
+            last = modified;
+            eprintln!("-- {} changed", path.display());
+            match rebuild(path, func, args) {
+                Ok(text) => println!("{}", text),
+                Err(err) => eprintln!("{}", err),
+            }
+        }
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+}
+
+/// One watch iteration: compiles and validates `path`, then invokes `func`.
+fn rebuild(path: &Path, func: Option<&str>, args: &[Value]) -> Result<String, Error> {
+    let binary = compile_source(path)?;
+    runtime::validate(binary.clone()).map_err(|errors| Error::Invalid(path.into(), errors))?;
+    match func {
+        Some(func) => {
+            let result =
+                runtime::Instance::new(binary, runtime::Config::default())?.call(func, args)?;
+            Ok(format!(">> {}", result))
+        }
+        None => Ok("ok".to_string()),
+    }
+}
+
+fn bench(
+    path: &Path,
+    func: &str,
+    args: &[Value],
+    warmup: usize,
+    iterations: usize,
+    format: OutputFormat,
+) -> Result<(), Error> {
+    let bench = runtime::bench(load_module(path)?, func, args, warmup, iterations)?;
+    match format {
+        OutputFormat::Text => println!("{}", bench.table()),
+        OutputFormat::Json => {
+            let report = json!({
+                "iterations": bench.iterations,
+                "min_ns": bench.min.as_nanos() as u64,
+                "mean_ns": bench.mean.as_nanos() as u64,
+                "p99_ns": bench.p99.as_nanos() as u64,
+                "instrs_per_call": bench.instrs,
+                "instrs_per_sec": bench.instrs_per_sec(),
+            });
+            println!("{:#}", report);
+        }
+    }
+    Ok(())
+}
+
+/// Calls `func` under the debugger, which reads commands from stdin.
+fn debug(path: &Path, func: &str, args: &[Value]) -> Result<(), Error> {
+    let mut config = runtime::Config::default();
+    config.hooks.push(Box::new(runtime::Debugger::stdio()));
+    let result = runtime::Instance::new(load_module(path)?, config)?.call(func, args)?;
+    println!(">> {}", result);
+    Ok(())
+}
+
+/// Prints the differences between two modules, failing if there are any.
+/// In JSON, differences are reported only through the error.
+fn diff(old: &Path, new: &Path, format: OutputFormat) -> Result<(), Error> {
+    let (old_module, _) = runtime::disassemble(load_module(old)?)?;
+    let (new_module, _) = runtime::disassemble(load_module(new)?)?;
+    let changes = runtime::diff(&old_module, &new_module);
+    match format {
+        OutputFormat::Text => changes.iter().for_each(|change| println!("{}", change)),
+        OutputFormat::Json if changes.is_empty() => println!("{:#}", json!({"changes": []})),
+        OutputFormat::Json => {}
+    }
+    if changes.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Differ(changes))
+    }
+}
+
+fn difftest(path: &Path, func: &str, args: &[Value], runtime: &str) -> Result<(), Error> {
+    let binary = load_module(path)?;
+    let reference = difftest::wasmtime(runtime, &binary, func, args)?;
+    let zod = difftest::zod(binary, func, args)?;
+    match difftest::compare(&zod, &reference) {
+        Some(divergence) => Err(Error::Divergence(divergence)),
+        None => {
+            println!("ok: both {}", zod);
+            Ok(())
+        }
+    }
+}
+
+fn graph(path: &Path, dot: bool, cfg: Option<String>) -> Result<(), Error> {
+    let (module, _) = runtime::disassemble(load_module(path)?)?;
+    let graph = match cfg {
+        Some(func) => {
+            let index = runtime::find_func(&module, &func).ok_or(Error::UnknownFunc(func))?;
+            runtime::cfg_dot(&module, index)
+        }
+        None if dot => runtime::call_graph_dot(&module),
+        None => runtime::call_graph(&module),
+    };
+    println!("{}", graph);
+    Ok(())
+}
+
+fn selftest() -> Result<(), Error> {
+    let results = selftest::run();
+    for (name, result) in &results {
+        match result {
+            Ok(()) => println!("ok      {}", name),
+            Err(err) => println!("FAILED  {}: {}", name, err),
+        }
+    }
+    let failed = results.iter().filter(|(_, r)| r.is_err()).count();
+    match failed {
+        0 => Ok(()),
+        failed => Err(Error::SelfTest {
+            failed,
+            total: results.len(),
+        }),
+    }
+}
+
+fn inspect_coredump(path: &Path) -> Result<(), Error> {
+    let bytes = read(path).map_err(|e| Error::Io(path.into(), e))?;
+    let dump = runtime::coredump::CoreDump::decode(&bytes)?;
+    println!("{}", dump);
+    Ok(())
+}
+
+fn info(path: &Path, format: OutputFormat) -> Result<(), Error> {
+    let info = runtime::info(load_module(path)?)?;
+    match format {
+        OutputFormat::Text => println!("{}", info.table()),
+        OutputFormat::Json => {
+            let producers: serde_json::Map<_, _> = info
+                .producers
+                .iter()
+                .map(|(field, values)| {
+                    let values: Vec<_> = values
+                        .iter()
+                        .map(|(name, version)| json!({"name": name, "version": version}))
+                        .collect();
+                    (field.clone(), json!(values))
+                })
+                .collect();
+            let sections: Vec<_> = info
+                .custom_sections
+                .iter()
+                .map(|s| json!({"name": s.name, "size": s.size}))
+                .collect();
+            let report = json!({
+                "size": info.size,
+                "version": info.version,
+                "name": info.name,
+                "producers": producers,
+                "target_features": info.target_features,
+                "custom_sections": sections,
+            });
+            println!("{:#}", report);
+        }
+    }
+    Ok(())
+}
+
+fn stats(path: &Path, format: OutputFormat) -> Result<(), Error> {
+    let stats = runtime::stats(load_module(path)?)?;
+    match format {
+        OutputFormat::Text => println!("{}", stats.table()),
+        OutputFormat::Json => {
+            let sections: Vec<_> = stats
+                .sections
+                .iter()
+                .map(|s| json!({"name": s.name, "offset": s.offset, "size": s.size}))
+                .collect();
+            let funcs: Vec<_> = stats
+                .funcs
+                .iter()
+                .map(|f| {
+                    json!({
+                        "index": f.index,
+                        "name": f.name,
+                        "size": f.size,
+                        "locals": f.locals,
+                        "instrs": f.instrs,
+                    })
+                })
+                .collect();
+            let report = json!({
+                "size": stats.size,
+                "sections": sections,
+                "funcs": funcs,
+                "instrs": stats.instrs,
+            });
+            println!("{:#}", report);
+        }
+    }
+    Ok(())
+}
+
+fn validate(path: &Path, format: OutputFormat) -> Result<(), Error> {
+    let binary = load_module(path)?;
+    runtime::validate(binary).map_err(|errors| Error::Invalid(path.into(), errors))?;
+    match format {
+        OutputFormat::Text => println!("ok"),
+        OutputFormat::Json => println!("{:#}", json!({"valid": true})),
+    }
+    Ok(())
+}
+
+/// Executes a function of the compiled module `binary`, built from `path`.
+fn invoke(binary: Vec<u8>, path: &Path, invoke: Invoke) -> Result<(), Error> {
+    let mut config = runtime::Config::new();
+    config.fuel = invoke.fuel;
+    if let Some(key_path) = &invoke.verify {
+        let key = read_key(key_path, VerifyingKey::from_public_key_pem)?;
+        config = config.with_verifying_key(key);
+    }
+    let mut coverage = None;
+    let mut profile = None;
+    let mut coredump = None;
+    if invoke.debug {
+        config.hooks.push(Box::new(runtime::Debugger::stdio()));
+    }
+    if let Some(funcs) = invoke.trace {
+        config
+            .hooks
+            .push(Box::new(runtime::Tracer::stderr().only(funcs)));
+    }
+    if let Some(lcov_path) = invoke.coverage {
+        let (module, code_map) = runtime::disassemble(binary.clone())?;
+        let hook = Rc::new(RefCell::new(runtime::Coverage::new(&module, &code_map)));
+        config.hooks.push(Box::new(hook.clone()));
+        coverage = Some((hook, lcov_path));
+    }
+    if let Some(dump_path) = invoke.coredump {
+        let hook = Rc::new(RefCell::new(runtime::coredump::CoreDumper::new(
+            path.to_string_lossy(),
+        )));
+        config.hooks.push(Box::new(hook.clone()));
+        coredump = Some((hook, dump_path));
+    }
+    if let Some(folded_path) = invoke.profile {
+        let hook = Rc::new(RefCell::new(runtime::Profiler::new()));
+        config.hooks.push(Box::new(hook.clone()));
+        profile = Some((hook, folded_path));
+    }
+
+    let script = invoke.script.is_some();
+    let format = invoke.format;
+    let calls = match (&invoke.script, &invoke.args_file) {
+        (Some(script_path), _) => read_script(script_path)?,
+        (None, Some(args_path)) => vec![(invoke.func.unwrap_or_default(), read_args(args_path)?)],
+        (None, None) => vec![(invoke.func.unwrap_or_default(), invoke.args)],
+    };
+    let timer = invoke
+        .timeout
+        .map(|timeout| (timeout, start_timer(config.interrupt.clone(), timeout)));
+    let mut results = vec![];
+    let module = match &invoke.cache_dir {
+        Some(dir) => runtime::cache::ModuleCache::new(dir).load(binary),
+        None => runtime::CompiledModule::new(binary).map(Arc::new),
+    };
+    let result = module.and_then(|module| {
+        let mut instance = runtime::Instance::from_module(module, config)?;
+        for (func, args) in &calls {
+            let result = instance.call(func, args)?;
+            if script && format == OutputFormat::Text {
+                println!("{} >> {}", show_call(func, args), result);
+            }
+            results.push(result);
+        }
+        Ok(())
+    });
+    let result = match timer {
+        Some((timeout, (cancel, timer))) => {
+            drop(cancel);
+            let timed_out = timer.join().unwrap_or(false);
+            result.map_err(|err| match err {
+                runtime::RuntimeError::Trap(_) if timed_out => Error::Timeout(timeout),
+                err => err.into(),
+            })
+        }
+        None => result.map_err(Error::from),
+    };
+
+    // Reports are written even when the function traps.
+    if let Some((coverage, lcov_path)) = coverage {
+        let coverage = coverage.borrow();
+        eprintln!("{}", coverage.summary());
+        if let Some(lcov_path) = lcov_path {
+            let lcov = coverage.lcov(&path.to_string_lossy());
+            write(&lcov_path, lcov).map_err(|e| Error::Io(lcov_path.clone(), e))?;
+        }
+    }
+
+    if let Some((profiler, folded_path)) = profile {
+        let profiler = profiler.borrow();
+        match folded_path {
+            Some(folded_path) => write(&folded_path, profiler.folded())
+                .map_err(|e| Error::Io(folded_path.clone(), e))?,
+            None => eprintln!("{}", profiler.table()),
+        }
+    }
+
+    if let Some((dumper, dump_path)) = coredump {
+        if let Some(dump) = dumper.borrow_mut().take() {
+            write(&dump_path, dump.encode()).map_err(|e| Error::Io(dump_path.clone(), e))?;
+            eprintln!("core dump written to {}", dump_path.display());
+        }
+    }
+
+    result?;
+    match format {
+        OutputFormat::Text if script => {}
+        OutputFormat::Text => println!(">> {}", results[0]),
+        OutputFormat::Json if script => {
+            let results: Vec<_> = calls
+                .iter()
+                .zip(results)
+                .map(|((func, args), result)| {
+                    let args: Vec<_> = args.iter().copied().map(value_json).collect();
+                    json!({"func": func, "args": args, "result": value_json(result)})
+                })
+                .collect();
+            println!("{:#}", json!({ "results": results }));
+        }
+        OutputFormat::Json => println!("{:#}", json!({ "result": value_json(results[0]) })),
+    }
+    Ok(())
+}
+
+/// Interrupts whatever runs on `handle` after `timeout`, unless the
+/// returned sender is dropped first. The thread returns whether it fired.
+fn start_timer(handle: InterruptHandle, timeout: Duration) -> (Sender<()>, JoinHandle<bool>) {
+    let (cancel, cancelled) = channel();
+    let timer = thread::spawn(move || match cancelled.recv_timeout(timeout) {
+        Err(RecvTimeoutError::Timeout) => {
+            handle.interrupt();
+            true
+        }
+        _ => false,
+    });
+    (cancel, timer)
+}
+
+/// Reads the calls of a script, one `func args...` per line. Blank lines
+/// and lines starting with `#` are skipped.
+fn read_script(path: &Path) -> Result<Vec<(String, Vec<Value>)>, Error> {
+    let text = read_to_string(path).map_err(|e| Error::Io(path.into(), e))?;
+    let mut calls = vec![];
+    for (line, text) in text.lines().enumerate() {
+        let mut words = text.split_whitespace();
+        let func = match words.next() {
+            None => continue,
+            Some(word) if word.starts_with('#') => continue,
+            Some(func) => func.to_string(),
+        };
+        let args = words
+            .map(str::parse)
+            .collect::<Result<Vec<Value>, _>>()
+            .map_err(|e| Error::Args(path.into(), format!("line {}: {}", line + 1, e)))?;
+        calls.push((func, args));
+    }
+    Ok(calls)
+}
+
+fn show_call(func: &str, args: &[Value]) -> String {
+    let args: Vec<String> = args.iter().map(|a| format!(" {}", a)).collect();
+    format!("{}{}", func, args.concat())
+}
+
+/// Reads arguments from a JSON array whose numbers are i32 values and
+/// whose strings are typed literals such as `"i64:9999999999"`.
+fn read_args(path: &Path) -> Result<Vec<Value>, Error> {
+    let invalid = |message: String| Error::Args(path.into(), message);
+    let text = read_to_string(path).map_err(|e| Error::Io(path.into(), e))?;
+    let json: serde_json::Value =
+        serde_json::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+    let items = json
+        .as_array()
+        .ok_or_else(|| invalid("expected an array of arguments".to_string()))?;
+    items
+        .iter()
+        .map(|item| match item {
+            serde_json::Value::String(s) => s.parse().map_err(invalid),
+            serde_json::Value::Number(n) => n.to_string().parse().map_err(invalid),
+            other => Err(invalid(format!("invalid argument {}", other))),
+        })
+        .collect()
+}
+
+fn value_json(value: Value) -> serde_json::Value {
+    match value {
+        Value::I32(v) => json!(v),
+        Value::I64(v) => json!(v),
+        Value::F64(v) => json!(v),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_format_by_extension_then_magic() {
+        let binary = b"\0asm\x01\0\0\0";
+
+        assert_eq!(Format::Text, detect_format(Path::new("add.zod"), binary));
+        assert_eq!(Format::Binary, detect_format(Path::new("add.wasm"), b"("));
+        assert_eq!(Format::Binary, detect_format(Path::new("-"), binary));
+        assert_eq!(Format::Text, detect_format(Path::new("add"), b"(module)"));
+    }
+
+    #[test]
+    fn read_args_from_json() {
+        let path = std::env::temp_dir().join(format!("zod-args-{}.json", std::process::id()));
+        write(&path, r#"[1, -2, "i64:9999999999", "f64:3.5"]"#).unwrap();
+        let args = read_args(&path);
+        write(&path, r#"[1, 2.5]"#).unwrap();
+        let invalid = read_args(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            vec![
+                Value::I32(1),
+                Value::I32(-2),
+                Value::I64(9999999999),
+                Value::F64(3.5)
+            ],
+            args.unwrap()
+        );
+        assert_eq!(
+            "error: invalid i32 value 2.5",
+            invalid
+                .unwrap_err()
+                .to_string()
+                .replace(&format!("{}: ", path.display()), "")
+        );
+    }
+
+    #[test]
+    fn read_script_skips_blanks_and_comments() {
+        let path = std::env::temp_dir().join(format!("zod-script-{}.txt", std::process::id()));
+        write(&path, "# setup\nadd 1 2\n\n  add -3 i32:4\n").unwrap();
+        let calls = read_script(&path);
+        write(&path, "add 1\nadd x\n").unwrap();
+        let invalid = read_script(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            vec![
+                ("add".to_string(), vec![Value::I32(1), Value::I32(2)]),
+                ("add".to_string(), vec![Value::I32(-3), Value::I32(4)]),
+            ],
+            calls.unwrap()
+        );
+        assert!(invalid
+            .unwrap_err()
+            .to_string()
+            .ends_with("line 2: invalid i32 value x"));
+    }
+
+    #[test]
+    fn rebuild_compiles_and_invokes() {
+        let path = std::env::temp_dir().join(format!("zod-watch-{}.zod", std::process::id()));
+        std::fs::copy("main.zod", &path).unwrap();
+
+        let args = [Value::I32(1), Value::I32(2)];
+        assert_eq!(">> 3", rebuild(&path, Some("add"), &args).unwrap());
+        assert!(matches!(
+            rebuild(&path, Some("add"), &[Value::I32(1), Value::F64(2.0)]),
+            Err(Error::Runtime(runtime::RuntimeError::InvalidArgType { .. }))
+        ));
+        assert_eq!("ok", rebuild(&path, None, &[]).unwrap());
+
+        write(&path, "(module (func)").unwrap();
+        assert!(matches!(rebuild(&path, None, &[]), Err(Error::Parse(..))));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn build_mirrors_sources_and_skips_up_to_date() {
+        let dir = std::env::temp_dir().join(format!("zod-build-{}", std::process::id()));
+        let (src, out) = (dir.join("src"), dir.join("out"));
+        create_dir_all(src.join("nested")).unwrap();
+        std::fs::copy("main.zod", src.join("add.zod")).unwrap();
+        std::fs::copy("main.zod", src.join("nested/add.wat")).unwrap();
+        write(src.join("notes.txt"), "not a module").unwrap();
+
+        let sources = find_sources(std::slice::from_ref(&src)).unwrap();
+        let built = build(std::slice::from_ref(&src), &out, false, None);
+        let fresh = up_to_date(&src.join("add.zod"), &out.join("add.bin"));
+        let pattern = find_sources(&[src.join("*/*.wat")]).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            vec![
+                (src.join("add.zod"), PathBuf::from("add.zod")),
+                (src.join("nested/add.wat"), PathBuf::from("nested/add.wat")),
+            ],
+            sources
+        );
+        assert!(built.is_ok());
+        assert!(fresh);
+        assert_eq!(
+            vec![(src.join("nested/add.wat"), PathBuf::from("nested/add.wat"))],
+            pattern
+        );
+    }
+
+    #[test]
+    fn write_output_refuses_to_overwrite_without_force() {
+        let dir = std::env::temp_dir().join(format!("zod-output-{}", std::process::id()));
+        let path = dir.join("nested/add.bin");
+
+        write_output(&path, b"first", false).unwrap();
+        assert!(matches!(
+            write_output(&path, b"second", false),
+            Err(Error::OutputExists(_))
+        ));
+        write_output(&path, b"third", true).unwrap();
+
+        assert_eq!(b"third".to_vec(), read_input(&path).unwrap());
+        std::fs::remove_dir_all(dir).unwrap();
     }
 }
@@ -1,3 +1,6 @@
+// The decoder only needs some of these; the compiler uses them all.
+#![cfg_attr(not(feature = "compiler"), allow(dead_code))]
+
 use crate::ast::ValueType;
 
 pub const MAGIC: &[u8] = &[0x00, 0x61, 0x73, 0x6d];
@@ -11,6 +14,7 @@ pub fn val_type(vt: &ValueType) -> u8 {
 }
 
 pub mod section {
+    pub const CUSTOM: u8 = 0x00;
     pub const TYPE: u8 = 0x01;
     pub const CODE: u8 = 0x0a;
     pub const FUNC: u8 = 0x03;
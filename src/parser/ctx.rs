@@ -47,34 +47,34 @@ impl Ctx {
         }
     }
 
-    pub fn get_func_idx(&self, idx: &Index) -> usize {
+    /// The index of a function, or `None` for an unknown identifier.
+    pub fn get_func_idx(&self, idx: &Index) -> Option<usize> {
         match idx {
-            Index::Idx(i) => *i,
+            Index::Idx(i) => Some(*i),
             Index::Id(id) => self
                 .funcs
                 .ids
                 .iter()
-                .position(|i| i == &Some(id.to_owned()))
-                .expect("Func id has to exists"),
+                .position(|i| i == &Some(id.to_owned())),
         }
     }
 
-    pub fn insert_local_id(&mut self, id: &Option<String>) {
+    /// Adds a local, returning `false` if its identifier is already taken
+    /// in the function.
+    pub fn insert_local_id(&mut self, id: &Option<String>) -> bool {
         if id.is_some() && self.locals.contains(id) {
-            panic!("Local identifiers have to be unique in the scope of the function")
+            false
         } else {
             self.locals.push((*id).clone());
+            true
         }
     }
 
-    pub fn get_local_idx(&self, index: &Index) -> usize {
+    /// The index of a local, or `None` for an unknown identifier.
+    pub fn get_local_idx(&self, index: &Index) -> Option<usize> {
         match index {
-            Index::Idx(i) => *i,
-            Index::Id(id) => self
-                .locals
-                .iter()
-                .position(|x| x == &Some(id.clone()))
-                .expect("Identifier not found"),
+            Index::Idx(i) => Some(*i),
+            Index::Id(id) => self.locals.iter().position(|x| x == &Some(id.clone())),
         }
     }
 
@@ -1,7 +1,7 @@
 use crate::ast::Instr;
 use crate::ast::Instr::I32Add;
 use crate::parser::ctx::Ctx;
-use crate::parser::token::bws;
+use crate::parser::token::{bws, failure};
 use crate::parser::types::index;
 use nom::branch::alt;
 use nom::bytes::complete::tag;
@@ -14,9 +14,11 @@ use std::rc::Rc;
 
 fn local_get<'a>(input: &'a str, ctx: &Rc<RefCell<Ctx>>) -> IResult<&'a str, Instr> {
     let local_get = bws(tag("local.get"));
-    let (input, i) = preceded(local_get, index)(input)?;
-    let i = ctx.borrow().get_local_idx(&i);
-    Ok((input, Instr::LocalGet(i)))
+    let (rest, i) = preceded(local_get, index)(input)?;
+    match ctx.borrow().get_local_idx(&i) {
+        Some(i) => Ok((rest, Instr::LocalGet(i))),
+        None => Err(failure(input)),
+    }
 }
 
 fn i32_add(input: &str) -> IResult<&str, Instr> {
@@ -45,6 +47,10 @@ mod tests {
             local_get("local.get $lhs", &ctx),
             Ok(("", Instr::LocalGet(0)))
         );
+        assert_eq!(
+            local_get("local.get $rhs", &ctx),
+            Err(failure("local.get $rhs"))
+        );
     }
 
     #[test]
@@ -1,4 +1,5 @@
 use crate::ast::Module;
+use std::fmt;
 
 mod ctx;
 mod instr;
@@ -7,7 +8,56 @@ mod token;
 mod types;
 mod values;
 
-pub fn parse(zod: &str) -> Module {
-    let (_, ast) = module::module(zod).expect("Ups, something went wrong!");
-    ast
+/// Where in the source text parsing failed, counting from 1.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl ParseError {
+    /// Locates `rest`, a suffix of `source`, in `source`.
+    fn at(source: &str, rest: &str) -> Self {
+        let consumed = &source[..source.len() - rest.len()];
+        let line = consumed.matches('\n').count() + 1;
+        let column = consumed.len() - consumed.rfind('\n').map_or(0, |i| i + 1) + 1;
+        Self { line, column }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "syntax error at {}:{}", self.line, self.column)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub fn parse(zod: &str) -> Result<Module, ParseError> {
+    match module::module(zod) {
+        Ok((_, ast)) => {
+            log::debug!(
+                "parsed {} types, {} functions and {} exports",
+                ast.types.len(),
+                ast.funcs.len(),
+                ast.exports.len()
+            );
+            Ok(ast)
+        }
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => Err(ParseError::at(zod, e.input)),
+        Err(nom::Err::Incomplete(_)) => Err(ParseError::at(zod, "")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_error_points_at_failing_form() {
+        let zod = "(module\n  (func $add (result i32)\n    local.get)\n)";
+
+        assert_eq!(Err(ParseError { line: 2, column: 3 }), parse(zod));
+    }
 }
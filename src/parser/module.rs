@@ -15,12 +15,15 @@ fn func<'a>(input: &'a str, ctx: &mut Rc<RefCell<Ctx>>) -> IResult<&'a str, Func
     fn inner<'a>(input: &'a str, ctx: &mut Rc<RefCell<Ctx>>) -> IResult<&'a str, Func> {
         let (input, id) = preceded(token::func, values::id)(input)?;
         ctx.borrow_mut().insert_func_id(Some(id.to_string()));
+        // Local identifiers are scoped to their function.
+        ctx.borrow_mut().locals.clear();
         let (input, f_type) = types::type_use(input, ctx)?;
+        let (input, locals) = types::locals(input, ctx)?;
         let (input, instrs) = instr::instrs(input, ctx)?;
 
         let f = Func {
             f_type: f_type as i32,
-            locals: vec![],
+            locals,
             body: instrs,
         };
 
@@ -37,16 +40,20 @@ fn func<'a>(input: &'a str, ctx: &mut Rc<RefCell<Ctx>>) -> IResult<&'a str, Func
 fn export<'a>(input: &'a str, ctx: &mut Rc<RefCell<Ctx>>) -> IResult<&'a str, Export> {
     let index = token::pt(preceded(token::func, types::index));
     let mut exp = token::pt(preceded(token::export, tuple((values::literal, index))));
-    let (input, (lit, idx)) = exp(input)?;
+    let (rest, (lit, idx)) = exp(input)?;
+    let idx = match ctx.borrow().get_func_idx(&idx) {
+        Some(idx) => idx,
+        None => return Err(token::failure(input)),
+    };
 
     let export = Export {
         name: lit.clone(),
-        e_desc: FuncExport(ctx.borrow().get_func_idx(&idx)),
+        e_desc: FuncExport(idx),
     };
 
     ctx.borrow_mut().insert_export(&Some(lit), &export);
 
-    Ok((input, export))
+    Ok((rest, export))
 }
 
 pub fn module(input: &str) -> IResult<&str, Module> {
@@ -72,7 +79,7 @@ pub fn module(input: &str) -> IResult<&str, Module> {
 mod tests {
     use super::*;
     use crate::ast::Instr::*;
-    use crate::ast::ValueType::I32;
+    use crate::ast::ValueType::{I32, I64};
     use crate::parser::ctx::Field;
 
     #[test]
@@ -169,4 +176,33 @@ mod tests {
 
         assert_eq!(module(zod), Ok(("", expected)));
     }
+
+    #[test]
+    fn local_ids_are_scoped_to_their_function() {
+        let zod = "(module
+                (func $a (param $x i32) (result i32) local.get $x)
+                (func $b (param $x i32) (result i32) local.get $x)
+                (export \"b\" (func $b))
+            )";
+
+        let (_, module) = module(zod).unwrap();
+
+        assert_eq!(vec![LocalGet(0)], module.funcs[1].body);
+        assert_eq!(FuncExport(1), module.exports[0].e_desc);
+    }
+
+    #[test]
+    fn locals_follow_the_params() {
+        let zod = "(module
+                (func $f (param $x i32) (result i32) (local i64) (local $y i32)
+                  local.get $y
+                  local.get $x
+                  i32.add)
+            )";
+
+        let (_, module) = module(zod).unwrap();
+
+        assert_eq!(vec![I64, I32], module.funcs[0].locals);
+        assert_eq!(vec![LocalGet(2), LocalGet(0), I32Add], module.funcs[0].body);
+    }
 }
@@ -23,6 +23,12 @@ where
     delimited(multispace0, inner, multispace0)
 }
 
+/// Stops parsing at `input` without trying alternatives, for source that
+/// is well-formed but refers to something that does not exist.
+pub fn failure(input: &str) -> nom::Err<nom::error::Error<&str>> {
+    nom::Err::Failure(nom::error::Error::new(input, nom::error::ErrorKind::Verify))
+}
+
 pub fn ws(input: &str) -> IResult<&str, &str> {
     multispace0(input)
 }
@@ -39,6 +45,10 @@ pub fn result(input: &str) -> IResult<&str, &str> {
     bws(tag("result"))(input)
 }
 
+pub fn local(input: &str) -> IResult<&str, &str> {
+    bws(tag("local"))(input)
+}
+
 pub fn export(input: &str) -> IResult<&str, &str> {
     bws(tag("export"))(input)
 }
@@ -83,6 +93,12 @@ mod tests {
         assert_eq!(result("result123"), Ok(("123", "result")));
     }
 
+    #[test]
+    fn local_parse() {
+        assert_eq!(local("local"), Ok(("", "local")));
+        assert!(local("param").is_err());
+    }
+
     #[test]
     fn export_parse() {
         assert_eq!(export(" export "), Ok(("", "export")));
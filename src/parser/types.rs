@@ -33,6 +33,7 @@ pub fn func_type<'a>(input: &'a str, ctx: &mut Rc<RefCell<Ctx>>) -> IResult<&'a
     );
 
     let t = alt((p, r));
+    let start = input;
     let (input, many_t) = many0(t)(input)?;
 
     let results = many_t
@@ -43,21 +44,44 @@ pub fn func_type<'a>(input: &'a str, ctx: &mut Rc<RefCell<Ctx>>) -> IResult<&'a
         })
         .collect::<Vec<ValueType>>();
 
-    let params = many_t
-        .iter()
-        .filter_map(|t| match t {
-            PR::R(_) => None,
-            PR::P(p, id) => {
-                ctx.borrow_mut().insert_local_id(id);
-                Some(*p)
+    let mut params = vec![];
+    for t in &many_t {
+        if let PR::P(p, id) = t {
+            if !ctx.borrow_mut().insert_local_id(id) {
+                return Err(token::failure(start));
             }
-        })
-        .collect::<Vec<ValueType>>();
+            params.push(*p);
+        }
+    }
 
     let ft = (params, results);
     Ok((input, ft))
 }
 
+/// The function's `(local ...)` declarations, which follow its type and
+/// are indexed after its params.
+pub fn locals<'a>(input: &'a str, ctx: &mut Rc<RefCell<Ctx>>) -> IResult<&'a str, Vec<ValueType>> {
+    let local = preceded(
+        ws,
+        token::pt(tuple((token::local, opt(values::id), types::value_type))),
+    );
+    let start = input;
+    let (input, many_l) = many0(local)(input)?;
+
+    let mut locals = vec![];
+    for (_, id, t) in many_l {
+        if !ctx
+            .borrow_mut()
+            .insert_local_id(&id.map(|id| id.to_string()))
+        {
+            return Err(token::failure(start));
+        }
+        locals.push(t);
+    }
+
+    Ok((input, locals))
+}
+
 pub fn value_type(input: &str) -> IResult<&str, ValueType> {
     let types = alt((value(I32, tag("i32")), value(I64, tag("i64"))));
     bws(types)(input)
@@ -170,6 +194,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn locals_parse() {
+        let mut ctx = Rc::new(RefCell::new(Ctx {
+            locals: vec![Some("$lhs".to_string())],
+            ..Ctx::new()
+        }));
+        assert_eq!(
+            locals("(local i64) (local $tmp i32) local.get 0", &mut ctx),
+            Ok((" local.get 0", vec![I64, I32]))
+        );
+        assert_eq!(
+            vec![Some("$lhs".to_string()), None, Some("$tmp".to_string())],
+            ctx.borrow().locals
+        );
+        assert!(locals("(local $lhs i32)", &mut ctx).is_err());
+    }
+
     #[test]
     fn value_type_parse() {
         assert_eq!(value_type("i32"), Ok(("", I32)));
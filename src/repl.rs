@@ -0,0 +1,270 @@
+use std::fs::read_to_string;
+use std::io::{BufRead, Write};
+use zod::ast::signature;
+use zod::runtime::{self, Config, Instance};
+use zod::{compiler, parser};
+
+const HELP: &str = "\
+(func ...), (export ...)  add a function or export to the session module
+<export> <args...>        invoke an exported function
+<instructions>            run instructions with the :args values as locals
+:args <values...>         set the locals used by instruction sequences
+:load <file.zod>          replace the session module with a source file
+:exports                  list the session's exports
+:source                   print the session module
+:reset                    start over with an empty module
+:help                     show this help
+:quit                     leave the repl";
+
+/// An interactive session that builds up a module one form at a time and
+/// runs code against it.
+pub struct Repl {
+    forms: Vec<String>,
+    args: Vec<i32>,
+    instance: Instance,
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        let instance = instantiate(&[]).expect("an empty module is valid");
+        Self {
+            forms: vec![],
+            args: vec![],
+            instance,
+        }
+    }
+
+    /// Evaluates one complete input, returning the text to show.
+    pub fn eval(&mut self, input: &str) -> Result<String, String> {
+        let input = input.trim();
+        let words: Vec<&str> = input.split_whitespace().collect();
+        match words.as_slice() {
+            [] => Ok(String::new()),
+            [":help"] => Ok(HELP.to_string()),
+            [":args", values @ ..] => {
+                self.args = parse_args(values)?;
+                Ok(format!("args = {:?}", self.args))
+            }
+            [":load", path] => {
+                let source = read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+                let forms = module_forms(&source);
+                self.instance = instantiate(&forms)?;
+                self.forms = forms;
+                Ok(format!("loaded {}", path))
+            }
+            [":exports"] => self.exports(),
+            [":source"] => Ok(source(&self.forms)),
+            [":reset"] => {
+                *self = Self::new();
+                Ok(String::new())
+            }
+            [command, ..] if command.starts_with(':') => Err(format!(
+                "unknown command {}, type :help for a list",
+                command
+            )),
+            _ if input.starts_with('(') => {
+                let forms = [self.forms.clone(), vec![input.to_string()]].concat();
+                self.instance = instantiate(&forms)?;
+                self.forms = forms;
+                Ok(String::new())
+            }
+            // Instructions are the only input containing a dot.
+            _ if input.contains('.') => self.run_instrs(input),
+            [func, args @ ..] => {
+                let args = parse_args(args)?;
+                show(self.instance.invoke(func, &args))
+            }
+        }
+    }
+
+    fn exports(&self) -> Result<String, String> {
+        let binary = compiler::compile(&parse(&source(&self.forms))?);
+        let (module, _) = runtime::disassemble(binary).map_err(|e| e.to_string())?;
+        let exports: Vec<String> = module
+            .exports
+            .iter()
+            .map(|e| {
+                let zod::ast::EDesc::FuncExport(index) = e.e_desc;
+                let f_type = &module.types[module.funcs[index].f_type as usize];
+                format!("{}: func {}", e.name, signature(f_type))
+            })
+            .collect();
+        Ok(exports.join("\n"))
+    }
+
+    /// Runs `instrs` as the body of a throwaway function taking the `:args`
+    /// values as i32 parameters.
+    fn run_instrs(&mut self, instrs: &str) -> Result<String, String> {
+        let params = " (param i32)".repeat(self.args.len());
+        let func = format!("(func $__repl{} (result i32) {})", params, instrs);
+        let export = "(export \"__repl\" (func $__repl))".to_string();
+        let forms = [self.forms.clone(), vec![func, export]].concat();
+        show(instantiate(&forms)?.invoke("__repl", &self.args))
+    }
+
+    /// Reads inputs until end of file or `:quit`. A form spanning several
+    /// lines is evaluated once its parentheses balance.
+    pub fn run(&mut self, input: &mut dyn BufRead, out: &mut dyn Write) {
+        let mut pending = String::new();
+        loop {
+            let prompt = if pending.is_empty() { "zod> " } else { "...> " };
+            let _ = write!(out, "{}", prompt);
+            let _ = out.flush();
+
+            let mut line = String::new();
+            match input.read_line(&mut line) {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {}
+            }
+            if pending.is_empty() && line.trim() == ":quit" {
+                return;
+            }
+            pending.push_str(&line);
+            if pending.matches('(').count() > pending.matches(')').count() {
+                continue;
+            }
+
+            let result = self.eval(&pending);
+            pending.clear();
+            match result {
+                Ok(text) if text.is_empty() => {}
+                Ok(text) => {
+                    let _ = writeln!(out, "{}", text);
+                }
+                Err(err) => {
+                    let _ = writeln!(out, "error: {}", err);
+                }
+            }
+        }
+    }
+}
+
+fn source(forms: &[String]) -> String {
+    let forms: Vec<String> = forms.iter().map(|f| format!("\n  {}", f)).collect();
+    format!("(module{}\n)", forms.concat())
+}
+
+/// The top-level forms of a module's source, without the `(module ...)`.
+fn module_forms(source: &str) -> Vec<String> {
+    let body = source.trim();
+    let body = body.strip_prefix("(module").unwrap_or(body);
+    let body = body.strip_suffix(')').unwrap_or(body);
+
+    let mut forms = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in body.char_indices() {
+        match c {
+            '(' if depth == 0 => {
+                start = i;
+                depth = 1;
+            }
+            '(' => depth += 1,
+            ')' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    forms.push(body[start..=i].to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    forms
+}
+
+fn parse(source: &str) -> Result<zod::ast::Module, String> {
+    parser::parse(source).map_err(|_| "syntax error".to_string())
+}
+
+fn instantiate(forms: &[String]) -> Result<Instance, String> {
+    let binary = compiler::compile(&parse(&source(forms))?);
+    runtime::validate(binary.clone()).map_err(|errors| {
+        let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        errors.join("\n")
+    })?;
+    Instance::new(binary, Config::default()).map_err(|e| e.to_string())
+}
+
+fn parse_args(values: &[&str]) -> Result<Vec<i32>, String> {
+    values
+        .iter()
+        .map(|v| v.parse().map_err(|_| format!("invalid argument {}", v)))
+        .collect()
+}
+
+fn show(result: Result<i32, runtime::RuntimeError>) -> Result<String, String> {
+    result.map(|r| r.to_string()).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const ADD: &str = "(func $add (param $lhs i32) (param $rhs i32) (result i32)
+        local.get $lhs local.get $rhs i32.add)";
+
+    #[test]
+    fn forms_build_up_the_module() {
+        let mut repl = Repl::new();
+
+        assert_eq!(Ok(String::new()), repl.eval(ADD));
+        assert_eq!(Ok(String::new()), repl.eval("(export \"add\" (func $add))"));
+        assert_eq!(Ok("5".to_string()), repl.eval("add 2 3"));
+        assert_eq!(
+            Ok("add: func (param i32 i32) (result i32)".to_string()),
+            repl.eval(":exports")
+        );
+    }
+
+    #[test]
+    fn instruction_sequences_use_args() {
+        let mut repl = Repl::new();
+
+        assert_eq!(Ok("args = [4, 5]".to_string()), repl.eval(":args 4 5"));
+        assert_eq!(
+            Ok("9".to_string()),
+            repl.eval("local.get 0 local.get 1 i32.add")
+        );
+        assert!(repl.forms.is_empty());
+    }
+
+    #[test]
+    fn invalid_forms_are_rejected() {
+        let mut repl = Repl::new();
+
+        assert_eq!(
+            Err("syntax error".to_string()),
+            repl.eval("(func $f (result i32) local.get $nope)")
+        );
+        assert!(repl
+            .eval("(func $f (result i32) i32.add)")
+            .unwrap_err()
+            .contains("operand stack underflow"));
+        assert!(repl.forms.is_empty());
+    }
+
+    #[test]
+    fn module_forms_splits_top_level() {
+        let source = format!("(module\n  {}\n  (export \"add\" (func $add))\n)", ADD);
+
+        assert_eq!(
+            vec![ADD.to_string(), "(export \"add\" (func $add))".to_string()],
+            module_forms(&source)
+        );
+    }
+
+    #[test]
+    fn run_waits_for_balanced_parens() {
+        let mut repl = Repl::new();
+        let mut input = Cursor::new("(func $id (param i32) (result i32)\n local.get 0)\n(export \"id\" (func $id))\nid 7\n:quit\nid 8\n");
+        let mut out = vec![];
+
+        repl.run(&mut input, &mut out);
+
+        assert_eq!(
+            "zod> ...> zod> zod> 7\nzod> ",
+            String::from_utf8(out).unwrap()
+        );
+    }
+}
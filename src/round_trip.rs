@@ -0,0 +1,163 @@
+//! Checks that a module survives compiling and decoding, and printing and
+//! parsing, unchanged. Any difference points at an encoder and decoder, or
+//! printer and parser, that disagree.
+
+use crate::ast::Module;
+use crate::parser::ParseError;
+use crate::runtime::{self, RuntimeError};
+use crate::{compiler, parser};
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum RoundTripError {
+    /// The compiled module could not be decoded.
+    Decode(RuntimeError),
+    /// The printed module, included here, could not be parsed.
+    Parse(ParseError, String),
+    /// The module came back different.
+    Mismatch {
+        expected: Box<Module>,
+        found: Box<Module>,
+    },
+}
+
+impl fmt::Display for RoundTripError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoundTripError::Decode(err) => write!(f, "compiled module does not decode: {}", err),
+            RoundTripError::Parse(err, text) => {
+                write!(f, "printed module does not parse: {}\n{}", err, text)
+            }
+            RoundTripError::Mismatch { expected, found } => {
+                write!(f, "module changed from\n{}\nto\n{}", expected, found)
+            }
+        }
+    }
+}
+
+/// Each message includes the error it wraps, so there is no separate
+/// source to report.
+impl std::error::Error for RoundTripError {}
+
+fn compare(expected: &Module, found: Module) -> Result<(), RoundTripError> {
+    if *expected == found {
+        Ok(())
+    } else {
+        Err(RoundTripError::Mismatch {
+            expected: Box::new(expected.clone()),
+            found: Box::new(found),
+        })
+    }
+}
+
+/// Checks that `disassemble(compile(module)) == module`.
+pub fn binary(module: &Module) -> Result<(), RoundTripError> {
+    let binary = compiler::compile(module);
+    let (decoded, _) = runtime::disassemble(binary).map_err(RoundTripError::Decode)?;
+    compare(module, decoded)
+}
+
+/// Checks that `parse(print(module)) == module`. Only modules the text
+/// format can express pass: no empty bodies, no unused types,
+/// and types listed in the order functions first use them.
+pub fn text(module: &Module) -> Result<(), RoundTripError> {
+    let text = module.to_string();
+    let parsed = parser::parse(&text).map_err(|e| RoundTripError::Parse(e, text))?;
+    compare(module, parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{EDesc, Export, Func, FuncType, Instr, ValueType};
+    use proptest::prelude::*;
+
+    fn value_types() -> impl Strategy<Value = Vec<ValueType>> {
+        prop::collection::vec(
+            prop_oneof![Just(ValueType::I32), Just(ValueType::I64)],
+            0..4,
+        )
+    }
+
+    fn instr() -> impl Strategy<Value = Instr> {
+        prop_oneof![(0..128usize).prop_map(Instr::LocalGet), Just(Instr::I32Add)]
+    }
+
+    /// Modules both formats can express: every count, index and size fits
+    /// the decoder's single bytes, and types are listed in the order the
+    /// functions first use them, as the parser collects them.
+    fn module() -> impl Strategy<Value = Module> {
+        let func = (
+            value_types(),
+            value_types(),
+            value_types(),
+            prop::collection::vec(instr(), 1..16),
+        );
+        let export = ("[a-z_]{1,8}", 0..128usize);
+        (
+            prop::collection::vec(func, 0..4),
+            prop::collection::vec(export, 0..4),
+        )
+            .prop_map(|(funcs, exports)| {
+                let mut types: Vec<FuncType> = vec![];
+                let funcs = funcs
+                    .into_iter()
+                    .map(|(params, results, locals, body)| {
+                        let f_type = (params, results);
+                        let index = match types.iter().position(|t| *t == f_type) {
+                            Some(index) => index,
+                            None => {
+                                types.push(f_type);
+                                types.len() - 1
+                            }
+                        };
+                        Func {
+                            f_type: index as i32,
+                            locals,
+                            body,
+                        }
+                    })
+                    .collect();
+                let exports = exports
+                    .into_iter()
+                    .map(|(name, index)| Export {
+                        name,
+                        e_desc: EDesc::FuncExport(index),
+                    })
+                    .collect();
+                Module {
+                    types,
+                    funcs,
+                    exports,
+                }
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn binary_round_trips(module in module()) {
+            prop_assert_eq!(Ok(()), binary(&module));
+        }
+
+        #[test]
+        fn text_round_trips(module in module()) {
+            prop_assert_eq!(Ok(()), text(&module));
+        }
+    }
+
+    #[test]
+    fn locals_round_trip() {
+        let module = Module {
+            types: vec![(vec![ValueType::I32], vec![ValueType::I32])],
+            funcs: vec![Func {
+                f_type: 0,
+                locals: vec![ValueType::I32, ValueType::I64],
+                body: vec![Instr::LocalGet(2), Instr::LocalGet(0), Instr::I32Add],
+            }],
+            exports: vec![],
+        };
+
+        assert_eq!(Ok(()), binary(&module));
+        assert_eq!(Ok(()), text(&module));
+    }
+}
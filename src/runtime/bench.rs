@@ -0,0 +1,120 @@
+use crate::runtime::config::Config;
+use crate::runtime::error::RuntimeError;
+use crate::runtime::hooks::{Hooks, Step};
+use crate::runtime::instance::Instance;
+use crate::runtime::module::CompiledModule;
+use crate::runtime::trap::TrapKind;
+use crate::runtime::value::Value;
+use std::cell::Cell;
+use std::fmt::Write;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Timings of repeated calls to one function.
+pub struct Bench {
+    pub iterations: usize,
+    pub min: Duration,
+    pub mean: Duration,
+    pub p99: Duration,
+    /// Instructions executed by one call.
+    pub instrs: u64,
+}
+
+/// Counts executed instructions.
+struct Counter(Rc<Cell<u64>>);
+
+impl Hooks for Counter {
+    fn on_instruction(&mut self, _step: &Step) -> Result<(), TrapKind> {
+        self.0.set(self.0.get() + 1);
+        Ok(())
+    }
+}
+
+/// Calls `func` `warmup` times untimed, then times `iterations` calls.
+/// The instructions are counted on a separate run, since a hook would
+/// slow down the timed ones.
+pub fn bench(
+    binary: Vec<u8>,
+    func: &str,
+    args: &[Value],
+    warmup: usize,
+    iterations: usize,
+) -> Result<Bench, RuntimeError> {
+    let module = Arc::new(CompiledModule::new(binary)?);
+
+    let instrs = Rc::new(Cell::new(0));
+    let mut config = Config::default();
+    config.hooks.push(Box::new(Counter(instrs.clone())));
+    Instance::from_module(module.clone(), config)?.call(func, args)?;
+
+    let mut instance = Instance::from_module(module, Config::default())?;
+    for _ in 0..warmup {
+        instance.call(func, args)?;
+    }
+    let mut times = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        instance.call(func, args)?;
+        times.push(start.elapsed());
+    }
+    times.sort();
+
+    let total: Duration = times.iter().sum();
+    let p99 = (iterations * 99).div_ceil(100).max(1) - 1;
+    Ok(Bench {
+        iterations,
+        min: times.first().copied().unwrap_or_default(),
+        mean: total / iterations.max(1) as u32,
+        p99: times.get(p99).copied().unwrap_or_default(),
+        instrs: instrs.get(),
+    })
+}
+
+impl Bench {
+    /// Instructions executed per second, going by the mean call time.
+    pub fn instrs_per_sec(&self) -> f64 {
+        match self.mean.as_secs_f64() {
+            secs if secs > 0.0 => self.instrs as f64 / secs,
+            _ => 0.0,
+        }
+    }
+
+    pub fn table(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "{:<12}  {:>12}", "iterations", self.iterations);
+        let _ = writeln!(out, "{:<12}  {:>12?}", "min", self.min);
+        let _ = writeln!(out, "{:<12}  {:>12?}", "mean", self.mean);
+        let _ = writeln!(out, "{:<12}  {:>12?}", "p99", self.p99);
+        let _ = writeln!(out, "{:<12}  {:>12}", "instrs/call", self.instrs);
+        let _ = write!(out, "{:<12}  {:>12.0}", "instrs/sec", self.instrs_per_sec());
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::testing::add_binary;
+
+    #[test]
+    fn bench_counts_and_orders_timings() {
+        let args = [Value::I32(1), Value::I32(2)];
+        let bench = bench(add_binary(), "add", &args, 2, 100).unwrap();
+
+        assert_eq!(100, bench.iterations);
+        assert_eq!(3, bench.instrs);
+        assert!(bench.min <= bench.mean && bench.min <= bench.p99);
+        assert!(bench.table().starts_with("iterations             100\n"));
+    }
+
+    #[test]
+    fn bench_reports_failing_calls() {
+        let result = bench(add_binary(), "sub", &[], 0, 1);
+
+        assert_eq!(
+            Some(RuntimeError::ExportNotFound("sub".to_string())),
+            result.err()
+        );
+    }
+}
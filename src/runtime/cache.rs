@@ -0,0 +1,251 @@
+use crate::ast::{EDesc, Export, Type, ValueType};
+use crate::runtime::error::RuntimeError;
+use crate::runtime::module::CompiledModule;
+use crate::runtime::reader::Reader;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const MAGIC: &[u8] = b"zodc";
+
+/// Decoded modules kept on disk, keyed by the SHA-256 hash of the binary
+/// and the runtime version, so loading an unchanged module again skips decoding
+/// its sections. Entries from other versions are never read, and a
+/// missing, stale or unreadable entry just means decoding as usual.
+pub struct ModuleCache {
+    dir: PathBuf,
+}
+
+impl ModuleCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Where the entry for `binary` lives.
+    pub fn entry(&self, binary: &[u8]) -> PathBuf {
+        let hash: String = hash(binary).iter().map(|b| format!("{:02x}", b)).collect();
+        let name = format!("{}-{}.zodc", hash, env!("CARGO_PKG_VERSION"));
+        self.dir.join(name)
+    }
+
+    /// Prepares `binary` from its cache entry, or decodes it and stores a
+    /// new entry.
+    pub fn load(&self, binary: Vec<u8>) -> Result<Arc<CompiledModule>, RuntimeError> {
+        let path = self.entry(&binary);
+        if let Ok(entry) = fs::read(&path) {
+            match decode(&entry, &binary) {
+                Ok(module) => {
+                    log::debug!("loaded {} from the module cache", path.display());
+                    return Ok(Arc::new(module));
+                }
+                Err(_) => log::warn!("ignoring invalid cache entry {}", path.display()),
+            }
+        }
+
+        let module = CompiledModule::new(binary)?;
+        if let Err(err) = store(&path, &encode(&module)) {
+            log::warn!("cannot write cache entry {}: {}", path.display(), err);
+        }
+        Ok(Arc::new(module))
+    }
+}
+
+/// Writes `bytes` to a temporary file first, so that other processes never
+/// read a half-written entry.
+fn store(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp = path.with_extension(format!("tmp{}", std::process::id()));
+    fs::write(&temp, bytes)?;
+    fs::rename(&temp, path)
+}
+
+fn hash(binary: &[u8]) -> [u8; 32] {
+    Sha256::digest(binary).into()
+}
+
+fn push_u32(out: &mut Vec<u8>, n: usize) {
+    out.extend_from_slice(&(n as u32).to_le_bytes());
+}
+
+fn value_type(t: &ValueType) -> u8 {
+    match t {
+        ValueType::I32 => 0x7f,
+        ValueType::I64 => 0x7e,
+    }
+}
+
+/// The sections of `module`, after the hash of its binary so that an entry
+/// is never used for a different module.
+fn encode(module: &CompiledModule) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    out.extend_from_slice(&hash(module.binary()));
+
+    push_u32(&mut out, module.types.len());
+    for (params, results) in &module.types {
+        for types in [params, results] {
+            push_u32(&mut out, types.len());
+            out.extend(types.iter().map(value_type));
+        }
+    }
+
+    push_u32(&mut out, module.exports.len());
+    for export in &module.exports {
+        let EDesc::FuncExport(index) = export.e_desc;
+        push_u32(&mut out, export.name.len());
+        out.extend_from_slice(export.name.as_bytes());
+        push_u32(&mut out, index);
+    }
+
+    push_u32(&mut out, module.f_types().len());
+    for f_type in module.f_types() {
+        push_u32(&mut out, *f_type as usize);
+    }
+
+    push_u32(&mut out, module.bodies().len());
+    for body in module.bodies() {
+        push_u32(&mut out, body.start);
+        push_u32(&mut out, body.end);
+    }
+    out
+}
+
+fn decode(entry: &[u8], binary: &[u8]) -> Result<CompiledModule, RuntimeError> {
+    let reader = Reader::new(entry.to_vec());
+    let count = |reader: &Reader| reader.dword().map(|n| n as usize);
+    if reader.bytes(MAGIC.len())? != MAGIC || reader.bytes(32)? != hash(binary) {
+        return Err(RuntimeError::WrongMagicHeader);
+    }
+
+    let value_types = |reader: &Reader| -> Result<Vec<ValueType>, RuntimeError> {
+        (0..count(reader)?)
+            .map(|_| {
+                let offset = reader.pos();
+                match reader.byte()? {
+                    0x7f => Ok(ValueType::I32),
+                    0x7e => Ok(ValueType::I64),
+                    _ => Err(RuntimeError::InvalidValueType { offset }),
+                }
+            })
+            .collect()
+    };
+    let types = (0..count(&reader)?)
+        .map(|_| Ok((value_types(&reader)?, value_types(&reader)?)))
+        .collect::<Result<Vec<Type>, RuntimeError>>()?;
+
+    let exports = (0..count(&reader)?)
+        .map(|_| {
+            let len = count(&reader)?;
+            let offset = reader.pos();
+            let name = std::str::from_utf8(reader.bytes(len)?)
+                .map_err(|_| RuntimeError::InvalidExportName { offset })?
+                .to_string();
+            let e_desc = EDesc::FuncExport(count(&reader)?);
+            Ok(Export { name, e_desc })
+        })
+        .collect::<Result<Vec<Export>, RuntimeError>>()?;
+
+    let f_types = (0..count(&reader)?)
+        .map(|_| Ok(reader.dword()? as i32))
+        .collect::<Result<Vec<i32>, RuntimeError>>()?;
+    let bodies = (0..count(&reader)?)
+        .map(|_| Ok(count(&reader)?..count(&reader)?))
+        .collect::<Result<Vec<Range<usize>>, RuntimeError>>()?;
+    if let Some(id) = reader.peek() {
+        let offset = reader.pos();
+        return Err(RuntimeError::InvalidSectionCode { id, offset });
+    }
+    // The module indexes bodies by function and slices the binary with
+    // them, so a corrupt entry must not get that far.
+    if f_types.len() != bodies.len() {
+        return Err(RuntimeError::CodeCountMismatch);
+    }
+    if let Some(body) = bodies
+        .iter()
+        .find(|body| body.start > body.end || body.end > binary.len())
+    {
+        return Err(RuntimeError::UnexpectedEnd { offset: body.start });
+    }
+
+    Ok(CompiledModule::from_sections(
+        types,
+        exports,
+        f_types,
+        binary.into(),
+        bodies,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::config::Config;
+    use crate::runtime::instance::Instance;
+    use crate::runtime::testing::add_binary;
+
+    #[test]
+    fn entries_round_trip() {
+        let module = CompiledModule::new(add_binary()).unwrap();
+        let decoded = decode(&encode(&module), &add_binary()).unwrap();
+
+        assert_eq!(module.types, decoded.types);
+        assert_eq!(module.exports, decoded.exports);
+        assert_eq!(module.f_types(), decoded.f_types());
+        assert_eq!(module.bodies(), decoded.bodies());
+    }
+
+    #[test]
+    fn load_writes_and_then_reads_entries() {
+        let dir = std::env::temp_dir().join(format!("zod-cache-{}", std::process::id()));
+        let cache = ModuleCache::new(&dir);
+        let entry = cache.entry(&add_binary());
+
+        let first = cache.load(add_binary()).unwrap();
+        assert!(entry.exists());
+        fs::write(&entry, b"zodc").unwrap();
+        let second = cache.load(add_binary()).unwrap();
+        let third = cache.load(add_binary()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        for module in [first, second, third] {
+            let mut instance = Instance::from_module(module, Config::default()).unwrap();
+            assert_eq!(Ok(3), instance.invoke("add", &[1, 2]));
+        }
+    }
+
+    #[test]
+    fn entries_for_other_binaries_are_rejected() {
+        let module = CompiledModule::new(add_binary()).unwrap();
+        let mut other = add_binary();
+        let add = other.len() - 2;
+        other[add] = 0x6b; // same length, different body
+
+        assert!(decode(&encode(&module), &add_binary()[..8]).is_err());
+        assert!(decode(&encode(&module), &other).is_err());
+    }
+
+    #[test]
+    fn corrupt_entries_are_rejected() {
+        let module = CompiledModule::new(add_binary()).unwrap();
+        let entry = encode(&module);
+        let body_end = entry.len() - 4;
+        let body_count = entry.len() - 12;
+
+        let mut past_the_end = entry.clone();
+        past_the_end[body_end] = 0xff;
+        assert_eq!(
+            Err(RuntimeError::UnexpectedEnd { offset: 34 }),
+            decode(&past_the_end, &add_binary()).map(|_| ())
+        );
+
+        let mut no_bodies = entry[..body_count].to_vec();
+        no_bodies.extend([0; 4]);
+        assert_eq!(
+            Err(RuntimeError::CodeCountMismatch),
+            decode(&no_bodies, &add_binary()).map(|_| ())
+        );
+    }
+}
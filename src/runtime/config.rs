@@ -0,0 +1,53 @@
+use crate::runtime::hooks::Hooks;
+use crate::runtime::interrupt::InterruptHandle;
+#[cfg(feature = "sign")]
+use crate::runtime::signature::VerifyingKey;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+/// Options controlling how the runtime executes a function.
+#[derive(Default)]
+pub struct Config {
+    /// Called, in order, on every interpreter event.
+    pub hooks: Vec<Box<dyn Hooks>>,
+    /// Checked before every instruction. Share one handle between configs
+    /// to stop several instances at once.
+    pub interrupt: InterruptHandle,
+    /// Instructions each invocation may run before it traps; `None` is
+    /// unlimited.
+    pub fuel: Option<u64>,
+    /// Only instantiate modules signed with this key.
+    #[cfg(feature = "sign")]
+    pub verifying_key: Option<VerifyingKey>,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `hook` after the hooks already registered.
+    pub fn with_hook(mut self, hook: impl Hooks + 'static) -> Self {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    pub fn with_interrupt(mut self, interrupt: InterruptHandle) -> Self {
+        self.interrupt = interrupt;
+        self
+    }
+
+    /// Makes each invocation trap with `OutOfFuel` once it has run `fuel`
+    /// instructions without returning.
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    /// Makes instantiation fail unless the module is signed with `key`.
+    #[cfg(feature = "sign")]
+    pub fn with_verifying_key(mut self, key: VerifyingKey) -> Self {
+        self.verifying_key = Some(key);
+        self
+    }
+}
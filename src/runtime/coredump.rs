@@ -0,0 +1,264 @@
+//! Core dumps: what an invocation was doing when it trapped, saved so that
+//! failures in long batch runs can be looked at after the fact. The runtime
+//! has no linear memory or globals, so a dump holds the trap and, for each
+//! frame of the backtrace, the instruction it stopped at, its locals and
+//! its operand stack.
+
+use crate::runtime::error::RuntimeError;
+use crate::runtime::hooks::{Hooks, TrapState};
+use crate::runtime::reader::Reader;
+use crate::runtime::trap::TrapKind;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt;
+
+const MAGIC: &[u8] = b"zodcore";
+
+/// A frame as it was when the trap happened.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FrameState {
+    pub func: usize,
+    pub name: Option<String>,
+    /// Byte offset of the instruction in the module.
+    pub offset: usize,
+    /// The trapping instruction, or `end`.
+    pub instr: String,
+    pub locals: Vec<i32>,
+    /// Bottom first.
+    pub stack: Vec<i32>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CoreDump {
+    /// Where the module came from, as given by whoever took the dump.
+    pub module: String,
+    pub trap: TrapKind,
+    /// Innermost frame first.
+    pub frames: Vec<FrameState>,
+}
+
+/// Turns the state an invocation traps in into a `CoreDump`.
+pub struct CoreDumper {
+    module: String,
+    dump: Option<CoreDump>,
+}
+
+impl CoreDumper {
+    pub fn new(module: impl Into<String>) -> Self {
+        Self {
+            module: module.into(),
+            dump: None,
+        }
+    }
+
+    /// The dump of the last trap, if there was one since the last call.
+    pub fn take(&mut self) -> Option<CoreDump> {
+        self.dump.take()
+    }
+}
+
+impl Hooks for CoreDumper {
+    fn on_trap(&mut self, state: &TrapState) {
+        self.dump = Some(CoreDump {
+            module: self.module.clone(),
+            trap: state.kind.clone(),
+            frames: vec![FrameState {
+                func: state.func.index,
+                name: state.func.name.map(str::to_string),
+                offset: state.offset,
+                instr: state
+                    .instr
+                    .map_or_else(|| "end".to_string(), |instr| instr.to_string()),
+                locals: state.locals.to_vec(),
+                stack: state.stack.to_vec(),
+            }],
+        });
+    }
+}
+
+fn push_u32(out: &mut Vec<u8>, n: usize) {
+    out.extend_from_slice(&(n as u32).to_le_bytes());
+}
+
+fn push_str(out: &mut Vec<u8>, s: &str) {
+    push_u32(out, s.len());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn push_values(out: &mut Vec<u8>, values: &[i32]) {
+    push_u32(out, values.len());
+    for value in values {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+impl CoreDump {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        push_str(&mut out, &self.module);
+        match self.trap {
+            TrapKind::StackUnderflow => push_u32(&mut out, 0),
+            TrapKind::LocalOutOfBounds(index) => {
+                push_u32(&mut out, 1);
+                push_u32(&mut out, index);
+            }
+            TrapKind::Interrupted => push_u32(&mut out, 2),
+            TrapKind::OutOfFuel => push_u32(&mut out, 3),
+        }
+
+        push_u32(&mut out, self.frames.len());
+        for frame in &self.frames {
+            push_u32(&mut out, frame.func);
+            push_str(&mut out, frame.name.as_deref().unwrap_or(""));
+            push_u32(&mut out, frame.offset);
+            push_str(&mut out, &frame.instr);
+            push_values(&mut out, &frame.locals);
+            push_values(&mut out, &frame.stack);
+        }
+        out
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, RuntimeError> {
+        let reader = Reader::new(bytes.to_vec());
+        let invalid = |_| RuntimeError::InvalidCoreDump;
+        let count = |reader: &Reader| reader.dword().map(|n| n as usize).map_err(invalid);
+        let string = |reader: &Reader| -> Result<String, RuntimeError> {
+            let len = count(reader)?;
+            let bytes = reader.bytes(len).map_err(invalid)?;
+            Ok(String::from_utf8_lossy(bytes).into_owned())
+        };
+        let read_values = |reader: &Reader| -> Result<Vec<i32>, RuntimeError> {
+            (0..count(reader)?)
+                .map(|_| count(reader).map(|n| n as i32))
+                .collect()
+        };
+
+        if reader.bytes(MAGIC.len()).ok() != Some(MAGIC) {
+            return Err(RuntimeError::InvalidCoreDump);
+        }
+        let module = string(&reader)?;
+        let trap = match count(&reader)? {
+            0 => TrapKind::StackUnderflow,
+            1 => TrapKind::LocalOutOfBounds(count(&reader)?),
+            2 => TrapKind::Interrupted,
+            3 => TrapKind::OutOfFuel,
+            _ => return Err(RuntimeError::InvalidCoreDump),
+        };
+        let frames = (0..count(&reader)?)
+            .map(|_| {
+                Ok(FrameState {
+                    func: count(&reader)?,
+                    name: Some(string(&reader)?).filter(|name| !name.is_empty()),
+                    offset: count(&reader)?,
+                    instr: string(&reader)?,
+                    locals: read_values(&reader)?,
+                    stack: read_values(&reader)?,
+                })
+            })
+            .collect::<Result<_, RuntimeError>>()?;
+        if reader.peek().is_some() {
+            return Err(RuntimeError::InvalidCoreDump);
+        }
+
+        Ok(Self {
+            module,
+            trap,
+            frames,
+        })
+    }
+}
+
+fn values(values: &[i32]) -> String {
+    let values: Vec<String> = values.iter().map(|v| format!(" {}", v)).collect();
+    values.concat()
+}
+
+impl fmt::Display for CoreDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "module: {}", self.module)?;
+        write!(f, "trap: {}", self.trap)?;
+        for (i, frame) in self.frames.iter().enumerate() {
+            write!(f, "\n\n{}: func[{}]", i, frame.func)?;
+            if let Some(name) = &frame.name {
+                write!(f, " <{}>", name)?;
+            }
+            write!(f, " @ {:#x}: {}", frame.offset, frame.instr)?;
+            write!(f, "\n  locals:{}", values(&frame.locals))?;
+            write!(f, "\n  stack:{}", values(&frame.stack))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::config::Config;
+    use crate::runtime::instance::Instance;
+    use crate::runtime::testing::add_binary;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn dump_of(fuel: u64) -> Option<CoreDump> {
+        let dumper = Rc::new(RefCell::new(CoreDumper::new("add.bin")));
+        let config = Config::new().with_hook(dumper.clone()).with_fuel(fuel);
+        let _ = Instance::new(add_binary(), config)
+            .unwrap()
+            .invoke("add", &[1, 2]);
+        let dump = dumper.borrow_mut().take();
+        dump
+    }
+
+    #[test]
+    fn traps_are_dumped_with_locals_and_stack() {
+        let dump = dump_of(2).unwrap();
+
+        assert_eq!(
+            CoreDump {
+                module: "add.bin".to_string(),
+                trap: TrapKind::OutOfFuel,
+                frames: vec![FrameState {
+                    func: 0,
+                    name: Some("add".to_string()),
+                    offset: 0x27,
+                    instr: "i32.add".to_string(),
+                    locals: vec![1, 2],
+                    stack: vec![1, 2],
+                }],
+            },
+            dump
+        );
+        assert_eq!(
+            "module: add.bin\n\
+             trap: all fuel consumed\n\
+             \n\
+             0: func[0] <add> @ 0x27: i32.add\n  \
+             locals: 1 2\n  \
+             stack: 1 2",
+            dump.to_string()
+        );
+    }
+
+    #[test]
+    fn returning_calls_leave_no_dump() {
+        assert_eq!(None, dump_of(100));
+    }
+
+    #[test]
+    fn dumps_round_trip() {
+        let mut dump = dump_of(2).unwrap();
+        dump.trap = TrapKind::LocalOutOfBounds(7);
+        dump.frames[0].stack = vec![-1, i32::MAX];
+
+        assert_eq!(Ok(dump.clone()), CoreDump::decode(&dump.encode()));
+        assert_eq!(
+            Err(RuntimeError::InvalidCoreDump),
+            CoreDump::decode(&add_binary())
+        );
+        assert_eq!(
+            Err(RuntimeError::InvalidCoreDump),
+            CoreDump::decode(&dump.encode()[..20])
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use crate::ast::{EDesc, Module};
+use crate::runtime::disassembler::CodeMap;
+use crate::runtime::hooks::{FuncInfo, Hooks, Step};
+use crate::runtime::trap::TrapKind;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt::Write;
+
+struct FuncCoverage {
+    name: String,
+    calls: usize,
+    offsets: Vec<usize>,
+    hits: Vec<usize>,
+}
+
+impl FuncCoverage {
+    fn covered(&self) -> usize {
+        self.hits.iter().filter(|h| **h > 0).count()
+    }
+}
+
+/// Counts how often each instruction of a module is executed.
+pub struct Coverage {
+    funcs: Vec<FuncCoverage>,
+}
+
+fn percent(covered: usize, total: usize) -> f64 {
+    if total == 0 {
+        100.0
+    } else {
+        covered as f64 * 100.0 / total as f64
+    }
+}
+
+impl Coverage {
+    pub fn new(module: &Module, code_map: &CodeMap) -> Self {
+        let name = |i: usize| {
+            module
+                .exports
+                .iter()
+                .find(|e| e.e_desc == EDesc::FuncExport(i))
+                .map(|e| e.name.clone())
+                .unwrap_or_else(|| format!("func[{}]", i))
+        };
+
+        let funcs = module
+            .funcs
+            .iter()
+            .enumerate()
+            .map(|(i, f)| FuncCoverage {
+                name: name(i),
+                calls: 0,
+                offsets: code_map[i][..f.body.len()].to_vec(),
+                hits: vec![0; f.body.len()],
+            })
+            .collect();
+
+        Self { funcs }
+    }
+
+    /// A table of covered instructions per function.
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+        let names = self.funcs.iter().map(|f| f.name.len());
+        let width = names.chain(Some("total".len())).max().unwrap_or(0);
+        let mut total = (0, 0);
+
+        for f in &self.funcs {
+            let (covered, all) = (f.covered(), f.hits.len());
+            let pct = percent(covered, all);
+            let _ = writeln!(
+                out,
+                "{:<width$}  {:>3}/{:<3} {:>5.1}%",
+                f.name, covered, all, pct
+            );
+            total = (total.0 + covered, total.1 + all);
+        }
+
+        let pct = percent(total.0, total.1);
+        let _ = write!(
+            out,
+            "{:<width$}  {:>3}/{:<3} {:>5.1}%",
+            "total", total.0, total.1, pct
+        );
+        out
+    }
+
+    /// An lcov tracefile for `source`. There is no source map yet, so each
+    /// instruction's byte offset in the module stands in for a line number.
+    pub fn lcov(&self, source: &str) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "TN:\nSF:{}", source);
+
+        for f in &self.funcs {
+            let line = f.offsets.first().copied().unwrap_or(0);
+            let _ = writeln!(out, "FN:{},{}", line, f.name);
+            let _ = writeln!(out, "FNDA:{},{}", f.calls, f.name);
+        }
+        let called = self.funcs.iter().filter(|f| f.calls > 0).count();
+        let _ = writeln!(out, "FNF:{}\nFNH:{}", self.funcs.len(), called);
+
+        for f in &self.funcs {
+            for (offset, hits) in f.offsets.iter().zip(&f.hits) {
+                let _ = writeln!(out, "DA:{},{}", offset, hits);
+            }
+        }
+        let found = self.funcs.iter().map(|f| f.hits.len()).sum::<usize>();
+        let hit = self.funcs.iter().map(|f| f.covered()).sum::<usize>();
+        let _ = write!(out, "LF:{}\nLH:{}\nend_of_record\n", found, hit);
+        out
+    }
+}
+
+impl Hooks for Coverage {
+    fn on_call_enter(&mut self, func: FuncInfo, _params: &[i32]) {
+        self.funcs[func.index].calls += 1;
+    }
+
+    fn on_instruction(&mut self, step: &Step) -> Result<(), TrapKind> {
+        self.funcs[step.func.index].hits[step.pc] += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::*;
+
+    fn coverage() -> Coverage {
+        let module = Module {
+            types: vec![(vec![ValueType::I32, ValueType::I32], vec![ValueType::I32])],
+            funcs: vec![Func {
+                f_type: 0,
+                locals: vec![],
+                body: vec![Instr::LocalGet(0), Instr::LocalGet(1), Instr::I32Add],
+            }],
+            exports: vec![Export {
+                name: "add".to_string(),
+                e_desc: EDesc::FuncExport(0),
+            }],
+        };
+        let mut coverage = Coverage::new(&module, &vec![vec![35, 37, 39, 40]]);
+
+        let info = FuncInfo {
+            index: 0,
+            name: Some("add"),
+        };
+        coverage.on_call_enter(info, &[1, 2]);
+        for pc in 0..2 {
+            let step = Step {
+                func: info,
+                pc,
+                offset: 0,
+                instr: &Instr::LocalGet(pc),
+                stack: &[],
+                locals: &[],
+            };
+            coverage.on_instruction(&step).unwrap();
+        }
+        coverage
+    }
+
+    #[test]
+    fn summary_lists_functions_and_total() {
+        assert_eq!(
+            "add      2/3    66.7%\n\
+             total    2/3    66.7%",
+            coverage().summary()
+        );
+    }
+
+    #[test]
+    fn lcov_reports_offsets_as_lines() {
+        assert_eq!(
+            "TN:\nSF:main.bin\n\
+             FN:35,add\nFNDA:1,add\nFNF:1\nFNH:1\n\
+             DA:35,1\nDA:37,1\nDA:39,0\n\
+             LF:3\nLH:2\nend_of_record\n",
+            coverage().lcov("main.bin")
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use crate::runtime::hooks::{Hooks, Step};
+use crate::runtime::trap::TrapKind;
+use std::io::{stdin, stdout, BufRead, BufReader, Write};
+
+const HELP: &str = "\
+commands:
+  s, step              execute the next instruction
+  c, continue          run until the next breakpoint
+  b, break <func|0xN>  break on entry to a function or at a byte offset
+  d, delete            remove all breakpoints
+  w, where             show the current instruction
+  stack                show the operand stack
+  locals               show the locals
+  globals              show the globals
+  memory               show the linear memory
+  q, quit              abort execution";
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Breakpoint {
+    Func(String),
+    Offset(usize),
+}
+
+/// Pauses execution on breakpoints and single steps, reading commands
+/// from `input` until told to resume.
+pub struct Debugger {
+    input: Box<dyn BufRead>,
+    out: Box<dyn Write>,
+    breakpoints: Vec<Breakpoint>,
+    stepping: bool,
+}
+
+impl Debugger {
+    /// Creates a debugger that stops before the first instruction.
+    pub fn new(input: Box<dyn BufRead>, out: Box<dyn Write>) -> Self {
+        Self {
+            input,
+            out,
+            breakpoints: Vec::new(),
+            stepping: true,
+        }
+    }
+
+    pub fn stdio() -> Self {
+        Self::new(Box::new(BufReader::new(stdin())), Box::new(stdout()))
+    }
+
+    fn hits(&self, step: &Step) -> bool {
+        self.breakpoints.iter().any(|b| match b {
+            Breakpoint::Func(f) => step.pc == 0 && Some(f.as_str()) == step.func.name,
+            Breakpoint::Offset(o) => *o == step.offset,
+        })
+    }
+
+    fn print(&mut self, line: &str) {
+        let _ = writeln!(self.out, "{}", line);
+    }
+}
+
+impl Hooks for Debugger {
+    fn on_instruction(&mut self, step: &Step) -> Result<(), TrapKind> {
+        if !self.stepping && !self.hits(step) {
+            return Ok(());
+        }
+
+        let location = format!(
+            "{} @ {:#06x}: {}",
+            step.func.name.unwrap_or("?"),
+            step.offset,
+            step.instr
+        );
+        self.print(&location);
+        loop {
+            let _ = write!(self.out, "(zod) ");
+            let _ = self.out.flush();
+            let mut line = String::new();
+            match self.input.read_line(&mut line) {
+                Ok(0) | Err(_) => return Err(TrapKind::Interrupted),
+                Ok(_) => {}
+            }
+
+            match line.split_whitespace().collect::<Vec<&str>>().as_slice() {
+                ["s"] | ["step"] => {
+                    self.stepping = true;
+                    return Ok(());
+                }
+                ["c"] | ["continue"] => {
+                    self.stepping = false;
+                    return Ok(());
+                }
+                ["b", target] | ["break", target] => {
+                    let breakpoint = match target.strip_prefix("0x") {
+                        Some(hex) => match usize::from_str_radix(hex, 16) {
+                            Ok(o) => Breakpoint::Offset(o),
+                            Err(_) => {
+                                self.print(&format!("invalid offset {}", target));
+                                continue;
+                            }
+                        },
+                        None => Breakpoint::Func(target.to_string()),
+                    };
+                    self.print(&format!("breakpoint {}", self.breakpoints.len()));
+                    self.breakpoints.push(breakpoint);
+                }
+                ["d"] | ["delete"] => self.breakpoints.clear(),
+                ["w"] | ["where"] => self.print(&location),
+                ["stack"] => self.print(&format!("{:?}", step.stack)),
+                ["locals"] => {
+                    for (i, local) in step.locals.iter().enumerate() {
+                        self.print(&format!("{}: {}", i, local));
+                    }
+                }
+                ["globals"] => self.print("module has no globals"),
+                ["memory"] => self.print("module has no linear memory"),
+                ["q"] | ["quit"] => return Err(TrapKind::Interrupted),
+                ["h"] | ["help"] => self.print(HELP),
+                [] => {}
+                _ => self.print("unknown command, type `help` for a list"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Instr;
+    use crate::runtime::hooks::FuncInfo;
+    use crate::runtime::testing::Buffer;
+    use std::io::Cursor;
+
+    fn debugger(commands: &str) -> (Debugger, Buffer) {
+        let out = Buffer::default();
+        let input = Cursor::new(commands.as_bytes().to_vec());
+        (Debugger::new(Box::new(input), Box::new(out.clone())), out)
+    }
+
+    fn step<'a>(offset: usize, pc: usize, instr: &'a Instr, stack: &'a [i32]) -> Step<'a> {
+        Step {
+            func: FuncInfo {
+                index: 0,
+                name: Some("add"),
+            },
+            pc,
+            offset,
+            instr,
+            stack,
+            locals: &[1, 2],
+        }
+    }
+
+    #[test]
+    fn stops_at_breakpoint_after_continue() {
+        let (mut debugger, out) = debugger("b 0x25\nc\nstack\nc\n");
+        let get = Instr::LocalGet(0);
+
+        let add = Instr::I32Add;
+
+        assert_eq!(Ok(()), debugger.on_instruction(&step(0x23, 0, &get, &[])));
+        assert_eq!(Ok(()), debugger.on_instruction(&step(0x25, 1, &get, &[1])));
+        assert_eq!(
+            Ok(()),
+            debugger.on_instruction(&step(0x27, 2, &add, &[1, 2]))
+        );
+
+        assert_eq!(
+            "add @ 0x0023: local.get 0\n\
+             (zod) breakpoint 0\n\
+             (zod) add @ 0x0025: local.get 0\n\
+             (zod) [1]\n\
+             (zod) ",
+            String::from_utf8(out.0.borrow().clone()).unwrap()
+        );
+    }
+
+    #[test]
+    fn quit_interrupts_execution() {
+        let (mut debugger, _) = debugger("quit\n");
+
+        assert_eq!(
+            Err(TrapKind::Interrupted),
+            debugger.on_instruction(&step(0x23, 0, &Instr::I32Add, &[]))
+        );
+    }
+}
@@ -0,0 +1,300 @@
+use crate::ast::{signature, EDesc, Func, FuncType, Instr, Module};
+use crate::runtime::graph::{label, names};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt;
+
+/// One difference between two modules. `item` names what differs, such as
+/// `type[1]`, `add (func[0])`, `add (func[0]) instr 2` or `export "add"`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Change {
+    Added {
+        item: String,
+        new: String,
+    },
+    Removed {
+        item: String,
+        old: String,
+    },
+    Changed {
+        item: String,
+        old: String,
+        new: String,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added { item, new } => write!(f, "+ {}: {}", item, new),
+            Change::Removed { item, old } => write!(f, "- {}: {}", item, old),
+            Change::Changed { item, old, new } => write!(f, "~ {}: {} -> {}", item, old, new),
+        }
+    }
+}
+
+/// Pairs up the items of two lists by position: both, only the old one, or
+/// only the new one.
+fn zip_longest<'a, T>(old: &'a [T], new: &'a [T]) -> Vec<(Option<&'a T>, Option<&'a T>)> {
+    (0..old.len().max(new.len()))
+        .map(|i| (old.get(i), new.get(i)))
+        .collect()
+}
+
+fn diff_types(old: &[FuncType], new: &[FuncType], changes: &mut Vec<Change>) {
+    for (index, pair) in zip_longest(old, new).into_iter().enumerate() {
+        let item = format!("type[{}]", index);
+        match pair {
+            (Some(old), Some(new)) if old != new => changes.push(Change::Changed {
+                item,
+                old: signature(old),
+                new: signature(new),
+            }),
+            (Some(old), None) => changes.push(Change::Removed {
+                item,
+                old: signature(old),
+            }),
+            (None, Some(new)) => changes.push(Change::Added {
+                item,
+                new: signature(new),
+            }),
+            _ => {}
+        }
+    }
+}
+
+/// How to turn `old` into `new` with the fewest removed and added
+/// instructions: `Some` indices into both for each kept instruction, and
+/// `None` on the other side for each removed or added one.
+fn edit_script(old: &[Instr], new: &[Instr]) -> Vec<(Option<usize>, Option<usize>)> {
+    // lcs[i][j] is the longest common subsequence of old[i..] and new[j..].
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut script = vec![];
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            script.push((Some(i), Some(j)));
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lcs[i + 1][j] > lcs[i][j + 1]) {
+            script.push((Some(i), None));
+            i += 1;
+        } else {
+            script.push((None, Some(j)));
+            j += 1;
+        }
+    }
+    script
+}
+
+/// Lists the instructions that differ, reporting removals and additions
+/// between the same kept instructions as changes where they pair up.
+fn diff_body(func: &str, old: &[Instr], new: &[Instr], changes: &mut Vec<Change>) {
+    let script = edit_script(old, new);
+    for run in script.split(|step| matches!(step, (Some(_), Some(_)))) {
+        let removed: Vec<usize> = run.iter().filter_map(|step| step.0).collect();
+        let added: Vec<usize> = run.iter().filter_map(|step| step.1).collect();
+        for pair in zip_longest(&removed, &added) {
+            changes.push(match pair {
+                (Some(&i), Some(&j)) => Change::Changed {
+                    item: format!("{} instr {}", func, i),
+                    old: old[i].to_string(),
+                    new: new[j].to_string(),
+                },
+                (Some(&i), None) => Change::Removed {
+                    item: format!("{} instr {}", func, i),
+                    old: old[i].to_string(),
+                },
+                (None, Some(&j)) => Change::Added {
+                    item: format!("{} instr {}", func, j),
+                    new: new[j].to_string(),
+                },
+                (None, None) => unreachable!("zip_longest pairs at least one item"),
+            });
+        }
+    }
+}
+
+fn func_signature(module: &Module, func: &Func) -> String {
+    match module.types.get(func.f_type as usize) {
+        Some(f_type) => signature(f_type),
+        None => format!("unknown type {}", func.f_type),
+    }
+}
+
+fn diff_funcs(old: &Module, new: &Module, changes: &mut Vec<Change>) {
+    let (old_names, new_names) = (names(old), names(new));
+    for (index, pair) in zip_longest(&old.funcs, &new.funcs).into_iter().enumerate() {
+        match pair {
+            (Some(old_func), Some(new_func)) => {
+                let item = label(index, new_names[index].or(old_names[index]));
+                let (old_sig, new_sig) =
+                    (func_signature(old, old_func), func_signature(new, new_func));
+                if old_sig != new_sig {
+                    changes.push(Change::Changed {
+                        item: format!("{} type", item),
+                        old: old_sig,
+                        new: new_sig,
+                    });
+                }
+                if old_func.locals != new_func.locals {
+                    let locals = |func: &Func| {
+                        let types: Vec<String> =
+                            func.locals.iter().map(|t| format!(" {}", t)).collect();
+                        format!("(local{})", types.concat())
+                    };
+                    changes.push(Change::Changed {
+                        item: format!("{} locals", item),
+                        old: locals(old_func),
+                        new: locals(new_func),
+                    });
+                }
+                diff_body(&item, &old_func.body, &new_func.body, changes);
+            }
+            (Some(old_func), None) => changes.push(Change::Removed {
+                item: label(index, old_names[index]),
+                old: func_signature(old, old_func),
+            }),
+            (None, Some(new_func)) => changes.push(Change::Added {
+                item: label(index, new_names[index]),
+                new: func_signature(new, new_func),
+            }),
+            (None, None) => {}
+        }
+    }
+}
+
+fn diff_exports(old: &Module, new: &Module, changes: &mut Vec<Change>) {
+    let target = |module: &Module, name: &str| {
+        module.exports.iter().find(|e| e.name == name).map(|e| {
+            let EDesc::FuncExport(index) = e.e_desc;
+            format!("func[{}]", index)
+        })
+    };
+    for export in &old.exports {
+        let item = format!("export \"{}\"", export.name);
+        let old_target = target(old, &export.name).unwrap_or_default();
+        match target(new, &export.name) {
+            None => changes.push(Change::Removed {
+                item,
+                old: old_target,
+            }),
+            Some(new_target) if new_target != old_target => changes.push(Change::Changed {
+                item,
+                old: old_target,
+                new: new_target,
+            }),
+            Some(_) => {}
+        }
+    }
+    for export in &new.exports {
+        if target(old, &export.name).is_none() {
+            changes.push(Change::Added {
+                item: format!("export \"{}\"", export.name),
+                new: target(new, &export.name).unwrap_or_default(),
+            });
+        }
+    }
+}
+
+/// The differences between two modules, section by section: types,
+/// then functions and their bodies, then exports. Functions and types are
+/// matched by index and exports by name.
+pub fn diff(old: &Module, new: &Module) -> Vec<Change> {
+    let mut changes = vec![];
+    diff_types(&old.types, &new.types, &mut changes);
+    diff_funcs(old, new, &mut changes);
+    diff_exports(old, new, &mut changes);
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Export, Instr::*, ValueType::*};
+
+    fn module(body: Vec<Instr>, export: &str) -> Module {
+        Module {
+            types: vec![(vec![I32, I32], vec![I32])],
+            funcs: vec![Func {
+                f_type: 0,
+                locals: vec![],
+                body,
+            }],
+            exports: vec![Export {
+                name: export.to_string(),
+                e_desc: EDesc::FuncExport(0),
+            }],
+        }
+    }
+
+    #[test]
+    fn identical_modules_have_no_changes() {
+        let add = module(vec![LocalGet(0), LocalGet(1), I32Add], "add");
+
+        assert_eq!(Vec::<Change>::new(), diff(&add, &add.clone()));
+    }
+
+    #[test]
+    fn bodies_are_compared_instruction_by_instruction() {
+        let old = module(vec![LocalGet(0), LocalGet(1), I32Add], "add");
+        let new = module(vec![LocalGet(1), LocalGet(1), I32Add, I32Add], "add");
+
+        let changes: Vec<String> = diff(&old, &new).iter().map(|c| c.to_string()).collect();
+
+        assert_eq!(
+            vec![
+                "~ add (func[0]) instr 0: local.get 0 -> local.get 1",
+                "+ add (func[0]) instr 3: i32.add",
+            ],
+            changes
+        );
+    }
+
+    #[test]
+    fn types_functions_and_exports_are_compared() {
+        let old = module(vec![LocalGet(0)], "first");
+        let mut new = module(vec![LocalGet(0)], "id");
+        new.types[0] = (vec![I64], vec![I64]);
+        new.funcs.push(new.funcs[0].clone());
+
+        assert_eq!(
+            vec![
+                Change::Changed {
+                    item: "type[0]".to_string(),
+                    old: "(param i32 i32) (result i32)".to_string(),
+                    new: "(param i64) (result i64)".to_string(),
+                },
+                Change::Changed {
+                    item: "id (func[0]) type".to_string(),
+                    old: "(param i32 i32) (result i32)".to_string(),
+                    new: "(param i64) (result i64)".to_string(),
+                },
+                Change::Added {
+                    item: "func[1]".to_string(),
+                    new: "(param i64) (result i64)".to_string(),
+                },
+                Change::Removed {
+                    item: "export \"first\"".to_string(),
+                    old: "func[0]".to_string(),
+                },
+                Change::Added {
+                    item: "export \"id\"".to_string(),
+                    new: "func[0]".to_string(),
+                },
+            ],
+            diff(&old, &new)
+        );
+    }
+}
@@ -1,52 +1,71 @@
 use crate::ast::*;
 use crate::op_codes::*;
 use crate::runtime::error::RuntimeError;
+use crate::runtime::module::CompiledModule;
 use crate::runtime::reader::Reader;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Byte offsets of each function's instructions in the module, including
+/// the trailing `end`.
+pub type CodeMap = Vec<Vec<usize>>;
+
+pub type Code = (StackType, Vec<Instr>, Vec<usize>);
 
 fn check_header(binary: &Reader) -> Result<(), RuntimeError> {
     if binary.len() < 8 {
         return Err(RuntimeError::ModuleToShort);
     }
 
-    if binary.bytes(4) != *b"\0asm" {
+    if binary.bytes(4)? != *b"\0asm" {
         return Err(RuntimeError::WrongMagicHeader);
     }
 
-    if binary.dword() != 1 {
+    if binary.dword()? != 1 {
         return Err(RuntimeError::WrongVersionHeader);
     }
 
     Ok(())
 }
 
-fn parse_type_section(binary: &Reader) -> Result<Vec<Type>, RuntimeError> {
-    if binary.byte() != section::TYPE {
-        return Err(RuntimeError::InvalidSectionCode);
+/// Reads the id of the next section, which must be `id`.
+fn expect_section(binary: &Reader, id: u8) -> Result<(), RuntimeError> {
+    let offset = binary.pos();
+    match binary.byte()? {
+        found if found == id => Ok(()),
+        found => Err(RuntimeError::InvalidSectionCode { id: found, offset }),
     }
-    let _size = binary.byte();
-    let num_types = binary.byte();
+}
+
+fn parse_type_section(binary: &Reader) -> Result<Vec<Type>, RuntimeError> {
+    expect_section(binary, section::TYPE)?;
+    let _size = binary.byte()?;
+    let num_types = binary.byte()?;
     let mut types = vec![];
 
     fn parse_valuetype(binary: &Reader) -> Result<ValueType, RuntimeError> {
-        match binary.byte() {
+        let offset = binary.pos();
+        match binary.byte()? {
             0x7f => Ok(ValueType::I32),
             0x7e => Ok(ValueType::I64),
-            _ => Err(RuntimeError::InvalidValueType),
+            _ => Err(RuntimeError::InvalidValueType { offset }),
         }
     }
 
     for _ in 0..num_types {
-        let _func = binary.byte();
+        let _func = binary.byte()?;
 
         // parse params
         let mut params = vec![];
-        for _ in 0..binary.byte() {
+        for _ in 0..binary.byte()? {
             params.push(parse_valuetype(binary)?);
         }
 
         // parse results
         let mut results = vec![];
-        for _ in 0..binary.byte() {
+        for _ in 0..binary.byte()? {
             results.push(parse_valuetype(binary)?);
         }
 
@@ -57,40 +76,39 @@ fn parse_type_section(binary: &Reader) -> Result<Vec<Type>, RuntimeError> {
 }
 
 fn parse_func_section(binary: &Reader) -> Result<Vec<i32>, RuntimeError> {
-    if binary.byte() != section::FUNC {
-        return Err(RuntimeError::InvalidSectionCode);
-    }
+    expect_section(binary, section::FUNC)?;
 
-    let _size = binary.byte();
-    let num = binary.byte();
+    let _size = binary.byte()?;
+    let num = binary.byte()?;
     let mut f_types = vec![];
 
     for _ in 0..num {
-        f_types.push(binary.byte() as i32)
+        f_types.push(binary.byte()? as i32)
     }
 
     Ok(f_types)
 }
 
 fn parse_export_section(binary: &Reader) -> Result<Vec<Export>, RuntimeError> {
-    if binary.byte() != section::EXPORT {
-        return Err(RuntimeError::InvalidSectionCode);
-    }
+    expect_section(binary, section::EXPORT)?;
 
-    let _size = binary.byte();
-    let num = binary.byte();
+    let _size = binary.byte()?;
+    let num = binary.byte()?;
     let mut exports = vec![];
 
     for _ in 0..num {
-        let length = binary.byte();
-        let name = match std::str::from_utf8(binary.bytes(length.into())) {
+        let length = binary.byte()?;
+        let offset = binary.pos();
+        let name = match core::str::from_utf8(binary.bytes(length.into())?) {
             Ok(n) => n.to_string(),
-            Err(_) => return Err(RuntimeError::InvalidExportName),
+            Err(_) => return Err(RuntimeError::InvalidExportName { offset }),
         };
-        let _zero = binary.byte();
-        let e_desc = match binary.byte() {
-            0x00 => EDesc::FuncExport(0),
-            _ => return Err(RuntimeError::InvalidExportType),
+        let offset = binary.pos();
+        let kind = binary.byte()?;
+        let index = binary.byte()? as usize;
+        let e_desc = match kind {
+            indices::FUNC => EDesc::FuncExport(index),
+            _ => return Err(RuntimeError::InvalidExportType { offset }),
         };
 
         exports.push(Export { name, e_desc })
@@ -99,53 +117,130 @@ fn parse_export_section(binary: &Reader) -> Result<Vec<Export>, RuntimeError> {
     Ok(exports)
 }
 
-pub fn parse_code_section(binary: &Reader) -> Result<Vec<(StackType, Vec<Instr>)>, RuntimeError> {
-    if binary.byte() != section::CODE {
-        return Err(RuntimeError::InvalidSectionCode);
-    };
+/// Decodes the body of function `func`, starting at its local
+/// declarations. The body must take exactly `size` bytes.
+pub fn parse_body(binary: &Reader, func: usize, size: usize) -> Result<Code, RuntimeError> {
+    let start = binary.pos();
+    let num_locals = binary.byte()? as i32;
+    let mut locals = vec![];
+    let mut instrs = vec![];
+    let mut offsets = vec![];
+
+    for _ in 0..num_locals {
+        let offset = binary.pos();
+        let vt = match binary.byte()? {
+            0x7f => ValueType::I32,
+            0x7e => ValueType::I64,
+            _ => return Err(RuntimeError::InvalidValueType { offset }),
+        };
+        locals.push(vt);
+    }
+
+    loop {
+        let offset = binary.pos();
+        offsets.push(offset);
+        let instr = match binary.byte()? {
+            0x20 => Instr::LocalGet(binary.byte()? as usize),
+            0x6a => Instr::I32Add,
+            0x0b => break,
+            _ => return Err(RuntimeError::InvalidInstruction { func, offset }),
+        };
 
-    let _size = binary.byte();
-    let num = binary.byte();
+        instrs.push(instr);
+    }
+
+    if binary.pos() != start + size {
+        return Err(RuntimeError::BodySizeMismatch {
+            func,
+            offset: start,
+        });
+    }
+    Ok((locals, instrs, offsets))
+}
+
+pub fn parse_code_section(binary: &Reader) -> Result<Vec<Code>, RuntimeError> {
+    expect_section(binary, section::CODE)?;
+
+    let _size = binary.byte()?;
+    let num = binary.byte()?;
     let mut code = vec![];
 
+    for func in 0..usize::from(num) {
+        let size = binary.byte()?;
+        code.push(parse_body(binary, func, size.into())?);
+    }
+
+    Ok(code)
+}
+
+/// Skips over the function bodies, returning the bytes each one takes.
+fn parse_code_section_lazy(binary: &Reader) -> Result<Vec<Range<usize>>, RuntimeError> {
+    expect_section(binary, section::CODE)?;
+
+    let _size = binary.byte()?;
+    let num = binary.byte()?;
+    let mut bodies = vec![];
+
     for _ in 0..num {
-        let _size = binary.byte();
-        let num_locals = binary.byte() as i32;
-        let mut locals = vec![];
-        let mut instrs = vec![];
-
-        for _ in 0..num_locals {
-            let vt = match binary.byte() {
-                0x7f => ValueType::I32,
-                0x7e => ValueType::I64,
-                _ => return Err(RuntimeError::InvalidValueType),
-            };
-            locals.push(vt);
-        }
+        let size = binary.byte()?;
+        let start = binary.pos();
+        binary.bytes(size.into())?;
+        bodies.push(start..binary.pos());
+    }
 
-        loop {
-            let instr = match binary.byte() {
-                0x20 => Instr::LocalGet(binary.byte() as usize),
-                0x6a => Instr::I32Add,
-                0x0b => break,
-                _ => return Err(RuntimeError::InvalidInstruction),
-            };
+    Ok(bodies)
+}
 
-            instrs.push(instr);
-        }
+/// Skips any custom sections that come next. They carry data for tools,
+/// such as signatures, and have no meaning to the runtime.
+fn skip_custom_sections(binary: &Reader) -> Result<(), RuntimeError> {
+    while binary.peek() == Some(section::CUSTOM) {
+        log::trace!("skipping custom section at {:#x}", binary.pos());
+        binary.byte()?;
+        let size = binary.var_u32()?;
+        binary.bytes(size as usize)?;
+    }
+    Ok(())
+}
 
-        code.push((locals, instrs));
+/// Parses the section with the given id if it comes next. Sections are
+/// optional, and the compiler leaves out empty ones.
+fn parse_optional<T: Default>(
+    binary: &Reader,
+    id: u8,
+    parse: impl Fn(&Reader) -> Result<T, RuntimeError>,
+) -> Result<T, RuntimeError> {
+    skip_custom_sections(binary)?;
+    if binary.peek() == Some(id) {
+        log::debug!("decoding section {} at {:#x}", id, binary.pos());
+        parse(binary)
+    } else {
+        log::trace!("no section {} at {:#x}", id, binary.pos());
+        Ok(T::default())
     }
+}
 
-    Ok(code)
+/// Checks that nothing follows the last section and that every declared
+/// function has a body.
+fn check_end(binary: &Reader, funcs: usize, bodies: usize) -> Result<(), RuntimeError> {
+    skip_custom_sections(binary)?;
+    if let Some(id) = binary.peek() {
+        let offset = binary.pos();
+        return Err(RuntimeError::InvalidSectionCode { id, offset });
+    }
+    if funcs != bodies {
+        return Err(RuntimeError::CodeCountMismatch);
+    }
+    Ok(())
 }
 
-pub fn parse_binary(binary: &Reader) -> Result<Module, RuntimeError> {
+pub fn parse_binary(binary: &Reader) -> Result<(Module, CodeMap), RuntimeError> {
     check_header(binary)?;
-    let types = parse_type_section(binary)?;
-    let funcs = parse_func_section(binary)?;
-    let exports = parse_export_section(binary)?;
-    let code = parse_code_section(binary)?;
+    let types = parse_optional(binary, section::TYPE, parse_type_section)?;
+    let funcs = parse_optional(binary, section::FUNC, parse_func_section)?;
+    let exports = parse_optional(binary, section::EXPORT, parse_export_section)?;
+    let code = parse_optional(binary, section::CODE, parse_code_section)?;
+    check_end(binary, funcs.len(), code.len())?;
 
     let join_code_func = || {
         funcs
@@ -159,16 +254,39 @@ pub fn parse_binary(binary: &Reader) -> Result<Module, RuntimeError> {
             .collect::<Vec<Func>>()
     };
 
-    Ok(Module {
+    let module = Module {
         types,
         exports,
         funcs: join_code_func(),
-    })
+    };
+    let code_map = code.into_iter().map(|c| c.2).collect();
+
+    Ok((module, code_map))
+}
+
+/// Decodes everything but the function bodies, which `CompiledModule`
+/// decodes on first use.
+pub fn parse_binary_lazy(binary: Reader) -> Result<CompiledModule, RuntimeError> {
+    check_header(&binary)?;
+    let types = parse_optional(&binary, section::TYPE, parse_type_section)?;
+    let funcs = parse_optional(&binary, section::FUNC, parse_func_section)?;
+    let exports = parse_optional(&binary, section::EXPORT, parse_export_section)?;
+    let bodies = parse_optional(&binary, section::CODE, parse_code_section_lazy)?;
+    check_end(&binary, funcs.len(), bodies.len())?;
+
+    Ok(CompiledModule::from_sections(
+        types,
+        exports,
+        funcs,
+        binary.into_data(),
+        bodies,
+    ))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::runtime::testing::add_binary;
 
     #[test]
     fn parse_code_section_test() {
@@ -188,13 +306,14 @@ mod tests {
         ];
         let reader = Reader::new(binary);
 
-        let (locals, instructions) = parse_code_section(&reader).unwrap()[0].clone();
+        let (locals, instructions, offsets) = parse_code_section(&reader).unwrap()[0].clone();
 
         assert_eq!(Vec::<ValueType>::new(), locals);
         assert_eq!(
             vec![Instr::LocalGet(0), Instr::LocalGet(1), Instr::I32Add],
             instructions
         );
+        assert_eq!(vec![5, 7, 9, 10], offsets);
     }
 
     #[test]
@@ -208,9 +327,8 @@ mod tests {
             0x61, // a
             0x64, // d
             0x64, // d
-            0x00, // 0
-            // export kind
-            0x00, // export func index
+            0x00, // export kind
+            0x01, // export func index
         ];
         let reader = Reader::new(binary);
 
@@ -219,7 +337,7 @@ mod tests {
         assert_eq!(
             vec![Export {
                 name: "add".to_string(),
-                e_desc: EDesc::FuncExport(0)
+                e_desc: EDesc::FuncExport(1)
             }],
             result
         );
@@ -278,8 +396,7 @@ mod tests {
             0x61, // a
             0x64, // d
             0x64, // d
-            0x00, // 0
-            // export kind
+            0x00, // export kind
             0x00, // export func index
             // section "Code" (10)
             0x0a, // section code
@@ -297,8 +414,9 @@ mod tests {
         ];
         let reader = Reader::new(binary);
 
-        let result = parse_binary(&reader).unwrap();
+        let (result, code_map) = parse_binary(&reader).unwrap();
 
+        assert_eq!(vec![vec![35, 37, 39, 40]], code_map);
         assert_eq!(
             Module {
                 types: vec![(vec![ValueType::I32, ValueType::I32], vec![ValueType::I32])],
@@ -316,6 +434,133 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_binary_without_optional_sections() {
+        let binary = vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x01, 0x00, // empty type section
+        ];
+        let reader = Reader::new(binary);
+
+        let (module, code_map) = parse_binary(&reader).unwrap();
+
+        assert_eq!(
+            Module {
+                types: vec![],
+                funcs: vec![],
+                exports: vec![],
+            },
+            module
+        );
+        assert!(code_map.is_empty());
+    }
+
+    #[test]
+    fn custom_sections_are_skipped() {
+        let binary = vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x00, 0x03, 0x01, 0x61, 0xff, // custom section "a"
+            0x01, 0x01, 0x00, // empty type section
+            0x00, 0x01, 0x00, // custom section ""
+        ];
+        let reader = Reader::new(binary);
+
+        let (module, _) = parse_binary(&reader).unwrap();
+
+        assert!(module.types.is_empty());
+        assert_eq!(19, reader.pos());
+    }
+
+    #[test]
+    fn custom_section_sizes_are_leb128() {
+        let mut binary = vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x00, 0x82, 0x01, 0x01, 0x61, // custom section "a" of 130 bytes
+        ];
+        binary.extend([0xff; 128]);
+        binary.extend([0x01, 0x01, 0x00]); // empty type section
+        let reader = Reader::new(binary);
+
+        assert!(parse_binary(&reader).is_ok());
+        assert_eq!(reader.len(), reader.pos());
+    }
+
+    #[test]
+    fn unexpected_sections_report_id_and_offset() {
+        let mut binary = add_binary();
+        let end = binary.len();
+        binary.extend([0x07, 0x00]);
+
+        let err = parse_binary(&Reader::new(binary)).unwrap_err();
+
+        assert_eq!(RuntimeError::InvalidSectionCode { id: 7, offset: end }, err);
+        assert_eq!(
+            format!("unexpected section 7 at {:#x}", end),
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn decode_errors_report_where_they_are() {
+        let mut invalid = add_binary();
+        invalid[40] = 0xff; // i32.add
+        let truncated = add_binary()[..38].to_vec();
+
+        let invalid = parse_binary(&Reader::new(invalid)).unwrap_err();
+        let truncated = parse_binary(&Reader::new(truncated)).unwrap_err();
+
+        assert_eq!(
+            RuntimeError::InvalidInstruction {
+                func: 0,
+                offset: 40
+            },
+            invalid
+        );
+        assert_eq!(
+            "invalid instruction in func[0] at 0x28",
+            invalid.to_string()
+        );
+        assert_eq!(RuntimeError::UnexpectedEnd { offset: 38 }, truncated);
+        assert_eq!("module ends inside the item at 0x26", truncated.to_string());
+    }
+
+    #[test]
+    fn bodies_must_end_at_their_size() {
+        let mut short = add_binary();
+        short[33] = 0x06; // body size
+        let mut long = add_binary();
+        long[31] = 0x0a; // section size
+        long[33] = 0x08; // body size
+        long.push(0x0b);
+
+        assert_eq!(
+            Err(RuntimeError::BodySizeMismatch {
+                func: 0,
+                offset: 34
+            }),
+            parse_binary(&Reader::new(short.clone())).map(|_| ())
+        );
+        assert!(parse_binary_lazy(Reader::new(short)).is_err());
+        assert_eq!(
+            Err(RuntimeError::BodySizeMismatch {
+                func: 0,
+                offset: 34
+            }),
+            parse_binary(&Reader::new(long.clone())).map(|_| ())
+        );
+        let module = parse_binary_lazy(Reader::new(long)).unwrap();
+        assert_eq!(
+            Err(RuntimeError::BodySizeMismatch {
+                func: 0,
+                offset: 34
+            }),
+            module.code(0).map(|_| ())
+        );
+    }
+
     #[test]
     fn check_header_test() {
         let binary = vec![
@@ -1,13 +1,130 @@
-#[derive(Debug, PartialEq, Eq)]
+use crate::ast::ValueType;
+use crate::runtime::trap::Trap;
+use alloc::string::String;
+use core::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RuntimeError {
     ModuleToShort,
+    /// The module ends inside the item that starts at `offset`.
+    UnexpectedEnd {
+        offset: usize,
+    },
     WrongMagicHeader,
     WrongVersionHeader,
-    InvalidSectionCode,
-    InvalidValueType,
-    InvalidExportType,
-    InvalidExportName,
-    InvalidInstruction,
-    ExportNotFound,
+    InvalidSectionCode {
+        id: u8,
+        offset: usize,
+    },
+    InvalidValueType {
+        offset: usize,
+    },
+    InvalidExportType {
+        offset: usize,
+    },
+    InvalidExportName {
+        offset: usize,
+    },
+    InvalidInstruction {
+        func: usize,
+        offset: usize,
+    },
+    IntegerTooLarge {
+        offset: usize,
+    },
+    /// The body of `func`, which starts at `offset`, does not end where its
+    /// size says.
+    BodySizeMismatch {
+        func: usize,
+        offset: usize,
+    },
+    CodeCountMismatch,
+    ExportNotFound(String),
+    InvalidExport,
     InvalidArgNumber,
+    InvalidArgType {
+        index: usize,
+        expected: ValueType,
+        found: String,
+    },
+    UnsupportedValueType(ValueType),
+    Unsigned,
+    InvalidSignature,
+    InvalidCoreDump,
+    Trap(Trap),
 }
+
+impl RuntimeError {
+    /// Byte offset in the module of the item that could not be decoded, if
+    /// the error is about one.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            RuntimeError::UnexpectedEnd { offset }
+            | RuntimeError::InvalidSectionCode { offset, .. }
+            | RuntimeError::InvalidValueType { offset }
+            | RuntimeError::InvalidExportType { offset }
+            | RuntimeError::InvalidExportName { offset }
+            | RuntimeError::InvalidInstruction { offset, .. }
+            | RuntimeError::IntegerTooLarge { offset }
+            | RuntimeError::BodySizeMismatch { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::ModuleToShort => write!(f, "module is shorter than its header"),
+            RuntimeError::UnexpectedEnd { offset } => {
+                write!(f, "module ends inside the item at {:#x}", offset)
+            }
+            RuntimeError::WrongMagicHeader => write!(f, "not a module: wrong magic bytes"),
+            RuntimeError::WrongVersionHeader => write!(f, "unsupported module version"),
+            RuntimeError::InvalidSectionCode { id, offset } => {
+                write!(f, "unexpected section {} at {:#x}", id, offset)
+            }
+            RuntimeError::InvalidValueType { offset } => {
+                write!(f, "invalid value type at {:#x}", offset)
+            }
+            RuntimeError::InvalidExportType { offset } => {
+                write!(f, "invalid export kind at {:#x}", offset)
+            }
+            RuntimeError::InvalidExportName { offset } => {
+                write!(f, "export name at {:#x} is not valid UTF-8", offset)
+            }
+            RuntimeError::InvalidInstruction { func, offset } => {
+                write!(f, "invalid instruction in func[{}] at {:#x}", func, offset)
+            }
+            RuntimeError::IntegerTooLarge { offset } => {
+                write!(f, "integer at {:#x} is too large", offset)
+            }
+            RuntimeError::BodySizeMismatch { func, offset } => write!(
+                f,
+                "body of func[{}] at {:#x} does not end where its size says",
+                func, offset
+            ),
+            RuntimeError::CodeCountMismatch => {
+                write!(f, "function and code sections have different lengths")
+            }
+            RuntimeError::ExportNotFound(name) => write!(f, "no export named {:?}", name),
+            RuntimeError::InvalidExport => write!(f, "export refers to a missing function or type"),
+            RuntimeError::InvalidArgNumber => write!(f, "wrong number of arguments"),
+            RuntimeError::InvalidArgType {
+                index,
+                expected,
+                found,
+            } => write!(f, "argument {} is {}, expected {}", index, found, expected),
+            RuntimeError::UnsupportedValueType(ty) => {
+                write!(f, "{} values are not supported by the interpreter", ty)
+            }
+            RuntimeError::Unsigned => write!(f, "module is not signed"),
+            RuntimeError::InvalidSignature => write!(f, "module signature does not verify"),
+            RuntimeError::InvalidCoreDump => write!(f, "not a valid core dump"),
+            RuntimeError::Trap(trap) => write!(f, "{}", trap),
+        }
+    }
+}
+
+impl core::error::Error for RuntimeError {}
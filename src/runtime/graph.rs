@@ -0,0 +1,174 @@
+use crate::ast::{signature, EDesc, Func, Instr, Module};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt::Write;
+
+/// The functions `func` calls, by index, in the order of the calls. The
+/// instruction set has no `call` yet, so for now this is always empty;
+/// the exhaustive match makes adding one fail to compile until it is
+/// handled here.
+fn callees(func: &Func) -> Vec<usize> {
+    func.body
+        .iter()
+        .filter_map(|instr| match instr {
+            Instr::LocalGet(_) | Instr::I32Add => None,
+        })
+        .collect()
+}
+
+/// The first name each function is exported under, if any.
+pub(crate) fn names(module: &Module) -> Vec<Option<&str>> {
+    let mut names = vec![None; module.funcs.len()];
+    for export in &module.exports {
+        let EDesc::FuncExport(index) = export.e_desc;
+        if let Some(name @ None) = names.get_mut(index) {
+            *name = Some(export.name.as_str());
+        }
+    }
+    names
+}
+
+pub(crate) fn label(index: usize, name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("{} (func[{}])", name, index),
+        None => format!("func[{}]", index),
+    }
+}
+
+/// Finds a function by export name or by index. Exports of functions
+/// the module does not define find nothing.
+pub fn find_func(module: &Module, func: &str) -> Option<usize> {
+    let by_name = module.exports.iter().find(|e| e.name == func);
+    let index = match by_name.map(|e| &e.e_desc) {
+        Some(EDesc::FuncExport(index)) => Some(*index),
+        None => func.parse().ok(),
+    };
+    index.filter(|i| *i < module.funcs.len())
+}
+
+/// Lists each function with the functions it calls.
+pub fn call_graph(module: &Module) -> String {
+    let names = names(module);
+    let lines: Vec<String> = module
+        .funcs
+        .iter()
+        .enumerate()
+        .map(|(index, func)| {
+            let callees: Vec<String> = callees(func)
+                .into_iter()
+                .map(|callee| label(callee, names.get(callee).copied().flatten()))
+                .collect();
+            let callees = if callees.is_empty() {
+                "-".to_string()
+            } else {
+                callees.join(", ")
+            };
+            format!("{} -> {}", label(index, names[index]), callees)
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// The call graph in Graphviz DOT, one node per function.
+pub fn call_graph_dot(module: &Module) -> String {
+    let names = names(module);
+    let mut out = String::from("digraph calls {\n    node [shape=box];\n");
+    for (index, func) in module.funcs.iter().enumerate() {
+        let f_type = module.types.get(func.f_type as usize);
+        let signature = f_type.map(signature).unwrap_or_default();
+        let label = format!("{}\\n{}", label(index, names[index]), signature);
+        let _ = writeln!(out, "    f{} [label=\"{}\"];", index, escape(&label));
+        for callee in callees(func) {
+            let _ = writeln!(out, "    f{} -> f{};", index, callee);
+        }
+    }
+    out.push('}');
+    out
+}
+
+/// The control-flow graph of a function in Graphviz DOT. Without branch
+/// instructions every body is one basic block.
+pub fn cfg_dot(module: &Module, index: usize) -> String {
+    let names = names(module);
+    let func = &module.funcs[index];
+    let instrs: Vec<String> = func
+        .body
+        .iter()
+        .map(|instr| format!("{}\\l", escape(&instr.to_string())))
+        .collect();
+    let title = escape(&label(index, names[index]));
+
+    let mut out = String::from("digraph cfg {\n    node [shape=box];\n");
+    let _ = writeln!(out, "    label=\"{}\";", title);
+    let _ = writeln!(out, "    entry [shape=oval];");
+    let _ = writeln!(out, "    b0 [label=\"{}\"];", instrs.concat());
+    let _ = writeln!(out, "    exit [shape=oval];");
+    let _ = writeln!(out, "    entry -> b0;");
+    let _ = writeln!(out, "    b0 -> exit;");
+    out.push('}');
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Export, ValueType::I32};
+
+    fn module() -> Module {
+        let func = |body| Func {
+            f_type: 0,
+            locals: vec![],
+            body,
+        };
+        Module {
+            types: vec![(vec![I32, I32], vec![I32])],
+            funcs: vec![
+                func(vec![Instr::LocalGet(0), Instr::LocalGet(1), Instr::I32Add]),
+                func(vec![Instr::LocalGet(0)]),
+            ],
+            exports: vec![Export {
+                name: "add".to_string(),
+                e_desc: EDesc::FuncExport(0),
+            }],
+        }
+    }
+
+    #[test]
+    fn call_graph_lists_every_function() {
+        assert_eq!("add (func[0]) -> -\nfunc[1] -> -", call_graph(&module()));
+        assert_eq!(
+            "digraph calls {\n    node [shape=box];\n    \
+             f0 [label=\"add (func[0])\\n(param i32 i32) (result i32)\"];\n    \
+             f1 [label=\"func[1]\\n(param i32 i32) (result i32)\"];\n}",
+            call_graph_dot(&module())
+        );
+    }
+
+    #[test]
+    fn cfg_is_one_block() {
+        let dot = cfg_dot(&module(), 0);
+
+        assert!(dot.contains("b0 [label=\"local.get 0\\llocal.get 1\\li32.add\\l\"];"));
+        assert!(dot.contains("entry -> b0;\n    b0 -> exit;\n}"));
+    }
+
+    #[test]
+    fn find_func_by_name_or_index() {
+        assert_eq!(Some(0), find_func(&module(), "add"));
+        assert_eq!(Some(1), find_func(&module(), "1"));
+        assert_eq!(None, find_func(&module(), "2"));
+    }
+
+    #[test]
+    fn find_func_ignores_exports_of_missing_functions() {
+        let mut module = module();
+        module.exports[0].e_desc = EDesc::FuncExport(2);
+
+        assert_eq!(None, find_func(&module, "add"));
+    }
+}
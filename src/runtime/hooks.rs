@@ -0,0 +1,73 @@
+use crate::ast::Instr;
+use crate::runtime::trap::TrapKind;
+use alloc::rc::Rc;
+use core::cell::RefCell;
+
+/// The function a hook is called for.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FuncInfo<'a> {
+    pub index: usize,
+    pub name: Option<&'a str>,
+}
+
+/// The interpreter state right before an instruction executes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Step<'a> {
+    pub func: FuncInfo<'a>,
+    /// Index of the instruction in the function body.
+    pub pc: usize,
+    /// Byte offset of the instruction in the module.
+    pub offset: usize,
+    pub instr: &'a Instr,
+    pub stack: &'a [i32],
+    pub locals: &'a [i32],
+}
+
+/// The interpreter state when a function traps.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TrapState<'a> {
+    pub func: FuncInfo<'a>,
+    pub kind: &'a TrapKind,
+    /// Byte offset of the trapping instruction in the module.
+    pub offset: usize,
+    /// `None` when the function traps at its `end`.
+    pub instr: Option<&'a Instr>,
+    pub stack: &'a [i32],
+    pub locals: &'a [i32],
+}
+
+/// Callbacks invoked by the interpreter while it executes a function.
+/// Every method has an empty default so implementors only override the
+/// events they care about.
+pub trait Hooks {
+    fn on_call_enter(&mut self, _func: FuncInfo, _params: &[i32]) {}
+
+    fn on_call_exit(&mut self, _func: FuncInfo, _result: &Result<i32, TrapKind>) {}
+
+    /// Returning an error stops execution with that trap.
+    fn on_instruction(&mut self, _step: &Step) -> Result<(), TrapKind> {
+        Ok(())
+    }
+
+    /// Called right before `on_call_exit` when the function traps.
+    fn on_trap(&mut self, _state: &TrapState) {}
+}
+
+/// Lets the caller keep a handle to a hook and inspect it after execution.
+impl<H: Hooks> Hooks for Rc<RefCell<H>> {
+    fn on_call_enter(&mut self, func: FuncInfo, params: &[i32]) {
+        self.borrow_mut().on_call_enter(func, params)
+    }
+
+    fn on_call_exit(&mut self, func: FuncInfo, result: &Result<i32, TrapKind>) {
+        self.borrow_mut().on_call_exit(func, result)
+    }
+
+    fn on_instruction(&mut self, step: &Step) -> Result<(), TrapKind> {
+        self.borrow_mut().on_instruction(step)
+    }
+
+    fn on_trap(&mut self, state: &TrapState) {
+        self.borrow_mut().on_trap(state)
+    }
+}
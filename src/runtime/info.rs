@@ -0,0 +1,229 @@
+use crate::op_codes::section;
+use crate::runtime::disassembler::parse_binary;
+use crate::runtime::error::RuntimeError;
+use crate::runtime::reader::Reader;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt::Write;
+
+/// Tool names and versions, as listed under a `producers` field.
+pub type Tools = Vec<(String, String)>;
+
+pub struct CustomSection {
+    pub name: String,
+    /// Size of the section's contents, name included.
+    pub size: usize,
+}
+
+/// What a module says about itself, read from its header and custom
+/// sections.
+pub struct Info {
+    pub size: usize,
+    /// The binary format version in the header.
+    pub version: u32,
+    /// The module name from the `name` section.
+    pub name: Option<String>,
+    /// Each `producers` field, such as `language` or `processed-by`, with
+    /// the tools and versions listed under it.
+    pub producers: Vec<(String, Tools)>,
+    /// Each feature from the `target_features` section, prefixed with
+    /// `+` if used, `-` if disallowed or `=` if required.
+    pub target_features: Vec<String>,
+    pub custom_sections: Vec<CustomSection>,
+}
+
+fn string(reader: &Reader) -> Result<String, RuntimeError> {
+    let len = reader.byte()?;
+    Ok(String::from_utf8_lossy(reader.bytes(len.into())?).into_owned())
+}
+
+/// The module name, subsection 0 of the `name` section.
+fn module_name(reader: &Reader) -> Result<Option<String>, RuntimeError> {
+    while reader.peek().is_some() {
+        let id = reader.byte()?;
+        let size = reader.byte()?;
+        if id == 0 {
+            return string(reader).map(Some);
+        }
+        reader.bytes(size.into())?;
+    }
+    Ok(None)
+}
+
+fn producers(reader: &Reader) -> Result<Vec<(String, Tools)>, RuntimeError> {
+    (0..reader.byte()?)
+        .map(|_| {
+            let field = string(reader)?;
+            let values = (0..reader.byte()?)
+                .map(|_| Ok((string(reader)?, string(reader)?)))
+                .collect::<Result<_, RuntimeError>>()?;
+            Ok((field, values))
+        })
+        .collect()
+}
+
+fn target_features(reader: &Reader) -> Result<Vec<String>, RuntimeError> {
+    (0..reader.byte()?)
+        .map(|_| {
+            let prefix = reader.byte()? as char;
+            Ok(format!("{}{}", prefix, string(reader)?))
+        })
+        .collect()
+}
+
+pub fn info(binary: Vec<u8>) -> Result<Info, RuntimeError> {
+    let size = binary.len();
+    let reader = Reader::new(binary);
+    parse_binary(&reader)?;
+
+    // Walk the section headers again, reading only the custom sections.
+    reader.seek(4);
+    let version = reader.dword()?;
+    let mut info = Info {
+        size,
+        version,
+        name: None,
+        producers: vec![],
+        target_features: vec![],
+        custom_sections: vec![],
+    };
+    while reader.pos() < size {
+        let id = reader.byte()?;
+        let contents = reader.bytes(reader.var_u32()? as usize)?;
+        if id != section::CUSTOM {
+            continue;
+        }
+        let contents = Reader::new(contents.to_vec());
+        let name = string(&contents)?;
+        match name.as_str() {
+            "name" => info.name = module_name(&contents)?,
+            "producers" => info.producers = producers(&contents)?,
+            "target_features" => info.target_features = target_features(&contents)?,
+            _ => {}
+        }
+        info.custom_sections.push(CustomSection {
+            name,
+            size: contents.len(),
+        });
+    }
+    Ok(info)
+}
+
+impl Info {
+    /// The metadata as a summary for the terminal.
+    pub fn table(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "module size: {} bytes", self.size);
+        let _ = write!(out, "version: {}", self.version);
+        if let Some(name) = &self.name {
+            let _ = write!(out, "\nname: {}", name);
+        }
+        for (field, values) in &self.producers {
+            let values: Vec<String> = values
+                .iter()
+                .map(|(name, version)| format!("{} {}", name, version).trim_end().to_string())
+                .collect();
+            let _ = write!(out, "\n{}: {}", field, values.join(", "));
+        }
+        if !self.target_features.is_empty() {
+            let _ = write!(out, "\ntarget features: {}", self.target_features.join(" "));
+        }
+
+        if !self.custom_sections.is_empty() {
+            let _ = write!(out, "\n\n{:<16}  {:>8}", "custom section", "size");
+            for s in &self.custom_sections {
+                let _ = write!(out, "\n{:<16}  {:>8}", s.name, s.size);
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::testing::add_binary;
+
+    fn custom(name: &str, contents: &[u8]) -> Vec<u8> {
+        let size = 1 + name.len() + contents.len();
+        [
+            &[section::CUSTOM, size as u8, name.len() as u8],
+            name.as_bytes(),
+            contents,
+        ]
+        .concat()
+    }
+
+    fn described_binary() -> Vec<u8> {
+        let name = [&[0x00, 0x04, 0x03][..], b"add"].concat();
+        let producers = [
+            &[0x01, 0x0c][..],
+            b"processed-by",
+            &[0x01, 0x03],
+            b"zod",
+            &[0x05],
+            b"0.1.0",
+        ]
+        .concat();
+        let features = [&[0x01, b'+', 0x0f][..], b"mutable-globals"].concat();
+        [
+            add_binary(),
+            custom("name", &name),
+            custom("producers", &producers),
+            custom("target_features", &features),
+            custom("build-id", &[0xab, 0xcd]),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn info_reads_known_custom_sections() {
+        let info = info(described_binary()).unwrap();
+
+        assert_eq!(1, info.version);
+        assert_eq!(Some("add".to_string()), info.name);
+        assert_eq!(
+            vec![(
+                "processed-by".to_string(),
+                vec![("zod".to_string(), "0.1.0".to_string())]
+            )],
+            info.producers
+        );
+        assert_eq!(vec!["+mutable-globals".to_string()], info.target_features);
+        let sections: Vec<(&str, usize)> = info
+            .custom_sections
+            .iter()
+            .map(|s| (s.name.as_str(), s.size))
+            .collect();
+        assert_eq!(
+            vec![
+                ("name", 11),
+                ("producers", 35),
+                ("target_features", 34),
+                ("build-id", 11)
+            ],
+            sections
+        );
+    }
+
+    #[test]
+    fn table_lists_every_part() {
+        let table = info(described_binary()).unwrap().table();
+
+        assert!(table.starts_with("module size: "));
+        assert!(table.contains("\nversion: 1\nname: add\nprocessed-by: zod 0.1.0\n"));
+        assert!(table.contains("\ntarget features: +mutable-globals\n"));
+        assert!(table.contains("\nbuild-id                11"));
+    }
+
+    #[test]
+    fn modules_without_custom_sections_have_no_metadata() {
+        let info = info(add_binary()).unwrap();
+
+        assert_eq!(41, info.size);
+        assert_eq!(None, info.name);
+        assert!(info.producers.is_empty() && info.custom_sections.is_empty());
+        assert_eq!("module size: 41 bytes\nversion: 1", info.table());
+    }
+}
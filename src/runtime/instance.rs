@@ -0,0 +1,417 @@
+use crate::ast::ValueType;
+use crate::runtime::config::Config;
+use crate::runtime::error::RuntimeError;
+use crate::runtime::interpreter::Execution;
+use crate::runtime::interrupt::InterruptHandle;
+use crate::runtime::module::CompiledModule;
+use crate::runtime::stack::Stack;
+use crate::runtime::trap::TrapKind;
+use crate::runtime::value::Value;
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+/// A loaded module ready to have its exports invoked.
+pub struct Instance {
+    module: Arc<CompiledModule>,
+    config: Config,
+    /// Operand stack kept between invocations to avoid reallocating it.
+    stack: Stack,
+}
+
+impl Instance {
+    pub fn new(binary: Vec<u8>, config: Config) -> Result<Self, RuntimeError> {
+        let module = CompiledModule::new(binary)?;
+        log::debug!(
+            "instantiating module with {} functions and {} exports",
+            module.func_count(),
+            module.exports.len()
+        );
+        Self::from_module(Arc::new(module), config)
+    }
+
+    /// Creates an instance of an already compiled module, sharing its code
+    /// with every other instance of it. Fails if `config` requires a
+    /// signature the module lacks.
+    pub fn from_module(module: Arc<CompiledModule>, config: Config) -> Result<Self, RuntimeError> {
+        module.check_signature(&config)?;
+        Ok(Self {
+            module,
+            config,
+            stack: Stack::new(),
+        })
+    }
+
+    /// Replaces the operand stack with one kept from an earlier instance.
+    #[cfg(feature = "std")]
+    pub(crate) fn with_stack(mut self, stack: Stack) -> Self {
+        self.stack = stack;
+        self
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn into_stack(self) -> Stack {
+        self.stack
+    }
+
+    pub fn invoke(&mut self, func: &str, params: &[i32]) -> Result<i32, RuntimeError> {
+        let mut execution = self.start(func, params)?;
+        let result = match self.resume(&mut execution, self.config.fuel) {
+            Ok(Poll::Ready(result)) => Ok(result),
+            Ok(Poll::Pending) => Err(self.abort(&mut execution, TrapKind::OutOfFuel)),
+            Err(err) => Err(err),
+        };
+        self.recycle(execution);
+        result
+    }
+
+    /// Invokes `func` with typed arguments, checking each against the
+    /// function's parameter types.
+    pub fn call(&mut self, func: &str, args: &[Value]) -> Result<Value, RuntimeError> {
+        let (_, f_type) = self.module.export_func(func)?;
+        if f_type.0.len() != args.len() {
+            return Err(RuntimeError::InvalidArgNumber);
+        }
+        let params = f_type
+            .0
+            .iter()
+            .zip(args)
+            .enumerate()
+            .map(|(index, (expected, arg))| match (expected, arg) {
+                (ValueType::I32, Value::I32(v)) => Ok(*v),
+                (ValueType::I64, Value::I64(_)) => {
+                    Err(RuntimeError::UnsupportedValueType(ValueType::I64))
+                }
+                (expected, arg) => Err(RuntimeError::InvalidArgType {
+                    index,
+                    expected: *expected,
+                    found: arg.ty().to_string(),
+                }),
+            })
+            .collect::<Result<Vec<i32>, _>>()?;
+        self.invoke(func, &params).map(Value::I32)
+    }
+
+    /// A handle that stops whatever this instance is running, from any
+    /// thread.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.config.interrupt.clone()
+    }
+
+    /// Prepares an invocation without running any instructions.
+    pub fn start(&mut self, func: &str, params: &[i32]) -> Result<Execution, RuntimeError> {
+        let (index, f_type) = self.module.export_func(func)?;
+        log::debug!("invoking {} (func[{}]) with {:?}", func, index, params);
+        let execution = Execution::new(index, func, f_type, params)?;
+        Ok(execution.with_stack(core::mem::take(&mut self.stack)))
+    }
+
+    /// Takes back the operand stack of a finished `execution` for the next
+    /// invocation to reuse.
+    pub fn recycle(&mut self, execution: Execution) {
+        self.stack = execution.into_stack();
+    }
+
+    /// Runs `execution` for at most `fuel` instructions.
+    pub fn resume(
+        &mut self,
+        execution: &mut Execution,
+        fuel: Option<u64>,
+    ) -> Result<Poll<i32>, RuntimeError> {
+        let code = self.module.code(execution.func())?;
+        execution.resume(code, &mut self.config, fuel)
+    }
+
+    /// Ends a paused `execution` with the given trap.
+    pub fn abort(&mut self, execution: &mut Execution, kind: TrapKind) -> RuntimeError {
+        match self.module.code(execution.func()) {
+            Ok(code) => execution.abort(code, &mut self.config, kind),
+            Err(err) => err,
+        }
+    }
+
+    /// Invokes `func` as a future that returns control to the executor
+    /// after every `yield_every` instructions. The config's fuel limits the
+    /// whole call, as it does for `invoke`.
+    pub fn invoke_async(
+        &mut self,
+        func: &str,
+        params: &[i32],
+        yield_every: u64,
+    ) -> Result<InvokeAsync<'_>, RuntimeError> {
+        let execution = self.start(func, params)?;
+        let fuel = self.config.fuel;
+        Ok(InvokeAsync {
+            instance: self,
+            execution: Some(execution),
+            yield_every,
+            fuel,
+        })
+    }
+}
+
+pub struct InvokeAsync<'a> {
+    instance: &'a mut Instance,
+    /// `None` once the call has finished and its stack was recycled.
+    execution: Option<Execution>,
+    yield_every: u64,
+    /// Instructions the call may still run; `None` is unlimited.
+    fuel: Option<u64>,
+}
+
+impl InvokeAsync<'_> {
+    fn finish(&mut self, result: Result<i32, RuntimeError>) -> Poll<Result<i32, RuntimeError>> {
+        if let Some(execution) = self.execution.take() {
+            self.instance.recycle(execution);
+        }
+        Poll::Ready(result)
+    }
+}
+
+impl Future for InvokeAsync<'_> {
+    type Output = Result<i32, RuntimeError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let execution = this
+            .execution
+            .as_mut()
+            .expect("InvokeAsync polled after completion");
+        let slice = match this.fuel {
+            Some(0) => {
+                let err = this.instance.abort(execution, TrapKind::OutOfFuel);
+                return this.finish(Err(err));
+            }
+            Some(fuel) => fuel.min(this.yield_every),
+            None => this.yield_every,
+        };
+        match this.instance.resume(execution, Some(slice)) {
+            Ok(Poll::Ready(result)) => this.finish(Ok(result)),
+            Ok(Poll::Pending) => {
+                this.fuel = this.fuel.map(|fuel| fuel - slice);
+                // Nothing to wait for; ask to be polled again right away.
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Err(err) => this.finish(Err(err)),
+        }
+    }
+}
+
+impl Drop for InvokeAsync<'_> {
+    /// Gives the stack back to the instance when a call is dropped
+    /// before it finishes.
+    fn drop(&mut self) {
+        if let Some(execution) = self.execution.take() {
+            self.instance.recycle(execution);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::testing::add_binary;
+    use crate::runtime::trap::Trap;
+    use std::task::Waker;
+
+    #[test]
+    fn invoke_add() {
+        let mut instance = Instance::new(add_binary(), Config::default()).unwrap();
+
+        assert_eq!(Ok(3), instance.invoke("add", &[1, 2]));
+        assert_eq!(Ok(7), instance.invoke("add", &[3, 4]));
+    }
+
+    #[test]
+    fn call_checks_argument_types() {
+        let mut instance = Instance::new(add_binary(), Config::default()).unwrap();
+
+        assert_eq!(
+            Ok(Value::I32(3)),
+            instance.call("add", &[Value::I32(1), Value::I32(2)])
+        );
+        assert_eq!(
+            Err(RuntimeError::InvalidArgType {
+                index: 1,
+                expected: ValueType::I32,
+                found: "f64".to_string(),
+            }),
+            instance.call("add", &[Value::I32(1), Value::F64(2.0)])
+        );
+    }
+
+    #[test]
+    fn instances_share_compiled_module() {
+        let module = Arc::new(CompiledModule::new(add_binary()).unwrap());
+        let mut first = Instance::from_module(module.clone(), Config::default()).unwrap();
+        let mut second = Instance::from_module(module.clone(), Config::default()).unwrap();
+
+        assert_eq!(Ok(3), first.invoke("add", &[1, 2]));
+        assert_eq!(Ok(7), second.invoke("add", &[3, 4]));
+        assert_eq!(3, Arc::strong_count(&module));
+    }
+
+    #[test]
+    fn instances_run_on_different_threads() {
+        let module = Arc::new(CompiledModule::new(add_binary()).unwrap());
+
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let module = module.clone();
+                std::thread::spawn(move || {
+                    let mut instance = Instance::from_module(module, Config::default()).unwrap();
+                    instance.invoke("add", &[i, i])
+                })
+            })
+            .collect();
+
+        for (i, thread) in threads.into_iter().enumerate() {
+            assert_eq!(Ok(2 * i as i32), thread.join().unwrap());
+        }
+    }
+
+    #[test]
+    fn invoke_traps_when_fuel_runs_out() {
+        let mut instance = Instance::new(add_binary(), Config::new().with_fuel(2)).unwrap();
+
+        assert!(matches!(
+            instance.invoke("add", &[1, 2]),
+            Err(RuntimeError::Trap(Trap {
+                kind: TrapKind::OutOfFuel,
+                ..
+            }))
+        ));
+
+        let mut instance = Instance::new(add_binary(), Config::new().with_fuel(3)).unwrap();
+        assert_eq!(Ok(3), instance.invoke("add", &[1, 2]));
+        assert_eq!(Ok(7), instance.invoke("add", &[3, 4]));
+    }
+
+    #[cfg(feature = "sign")]
+    #[test]
+    fn new_checks_the_signature_when_required() {
+        use crate::runtime::signature::{sign, SigningKey};
+
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let config = || Config::new().with_verifying_key(key.verifying_key());
+
+        assert!(matches!(
+            Instance::new(add_binary(), config()),
+            Err(RuntimeError::Unsigned)
+        ));
+        let mut instance = Instance::new(sign(&add_binary(), &key), config()).unwrap();
+        assert_eq!(Ok(3), instance.invoke("add", &[1, 2]));
+    }
+
+    #[cfg(feature = "sign")]
+    #[test]
+    fn from_module_checks_the_signature_when_required() {
+        use crate::runtime::signature::{sign, SigningKey};
+
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let config = || Config::new().with_verifying_key(key.verifying_key());
+        let unsigned = Arc::new(CompiledModule::new(add_binary()).unwrap());
+        let signed = Arc::new(CompiledModule::new(sign(&add_binary(), &key)).unwrap());
+
+        assert!(matches!(
+            Instance::from_module(unsigned.clone(), config()),
+            Err(RuntimeError::Unsigned)
+        ));
+        assert!(Instance::from_module(unsigned, Config::default()).is_ok());
+        for _ in 0..2 {
+            let mut instance = Instance::from_module(signed.clone(), config()).unwrap();
+            assert_eq!(Ok(3), instance.invoke("add", &[1, 2]));
+        }
+    }
+
+    #[test]
+    fn interrupt_stops_next_instruction() {
+        let mut instance = Instance::new(add_binary(), Config::default()).unwrap();
+        let handle = instance.interrupt_handle();
+        let mut execution = instance.start("add", &[1, 2]).unwrap();
+
+        assert_eq!(Ok(Poll::Pending), instance.resume(&mut execution, Some(1)));
+        handle.interrupt();
+        assert!(matches!(
+            instance.resume(&mut execution, Some(1)),
+            Err(RuntimeError::Trap(Trap {
+                kind: TrapKind::Interrupted,
+                ..
+            }))
+        ));
+        assert_eq!(Ok(3), instance.invoke("add", &[1, 2]));
+    }
+
+    #[test]
+    fn invoke_async_yields_between_slices() {
+        let mut instance = Instance::new(add_binary(), Config::default()).unwrap();
+        let mut future = instance.invoke_async("add", &[1, 2], 1).unwrap();
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let mut pending = 0;
+        let result = loop {
+            match Pin::new(&mut future).poll(&mut cx) {
+                Poll::Ready(result) => break result,
+                Poll::Pending => pending += 1,
+            }
+        };
+
+        assert_eq!(Ok(3), result);
+        assert_eq!(2, pending);
+    }
+
+    #[test]
+    fn invoke_async_traps_when_fuel_runs_out() {
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut run = |fuel| {
+            let mut instance = Instance::new(add_binary(), Config::new().with_fuel(fuel)).unwrap();
+            let mut future = instance.invoke_async("add", &[1, 2], 1).unwrap();
+            (0..10)
+                .find_map(|_| match Pin::new(&mut future).poll(&mut cx) {
+                    Poll::Ready(result) => Some(result),
+                    Poll::Pending => None,
+                })
+                .expect("the call ends within ten slices")
+        };
+
+        assert!(matches!(
+            run(2),
+            Err(RuntimeError::Trap(Trap {
+                kind: TrapKind::OutOfFuel,
+                ..
+            }))
+        ));
+        assert_eq!(Ok(3), run(3));
+    }
+
+    #[test]
+    fn invoke_async_recycles_the_stack() {
+        let mut instance = Instance::new(add_binary(), Config::default()).unwrap();
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let mut future = instance.invoke_async("add", &[1, 2], 10).unwrap();
+        assert_eq!(Poll::Ready(Ok(3)), Pin::new(&mut future).poll(&mut cx));
+        drop(future);
+        assert!(instance.stack.capacity() > 0);
+
+        instance.stack = Stack::new();
+        let mut future = instance.invoke_async("add", &[1, 2], 1).unwrap();
+        assert_eq!(Poll::Pending, Pin::new(&mut future).poll(&mut cx));
+        drop(future);
+        assert!(instance.stack.capacity() > 0);
+    }
+
+    #[test]
+    fn invoke_async_reports_missing_export() {
+        let mut instance = Instance::new(add_binary(), Config::default()).unwrap();
+
+        assert!(matches!(
+            instance.invoke_async("sub", &[], 1),
+            Err(RuntimeError::ExportNotFound(name)) if name == "sub"
+        ));
+    }
+}
@@ -1,31 +1,222 @@
 use crate::ast::*;
+use crate::runtime::config::Config;
 use crate::runtime::error::RuntimeError;
-use crate::runtime::error::RuntimeError::ExportNotFound;
-use crate::runtime::processor::Processor;
+use crate::runtime::hooks::{FuncInfo, Step, TrapState};
+use crate::runtime::module::Code;
+use crate::runtime::processor::{OnInstr, Processor};
+use crate::runtime::stack::Stack;
+use crate::runtime::trap::{Frame, Trap, TrapKind};
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::task::Poll;
 
-pub fn invoke_function(ast: &Module, func: &str, params: &[i32]) -> Result<i32, RuntimeError> {
-    let export = match ast.exports.iter().find(|e| e.name == func) {
-        None => return Err(ExportNotFound),
-        Some(e) => e,
-    };
+/// An invocation of a function that can be executed in slices.
+pub struct Execution {
+    func: usize,
+    name: String,
+    params: Vec<i32>,
+    /// The params followed by the function's declared locals.
+    locals: Vec<i32>,
+    processor: Processor,
+    started: bool,
+}
+
+impl Execution {
+    pub fn new(
+        func: usize,
+        name: &str,
+        f_type: &FuncType,
+        params: &[i32],
+    ) -> Result<Self, RuntimeError> {
+        if f_type.0.len() != params.len() {
+            return Err(RuntimeError::InvalidArgNumber);
+        };
+
+        Ok(Self {
+            func,
+            name: name.to_string(),
+            params: params.to_vec(),
+            locals: vec![],
+            processor: Processor::new(),
+            started: false,
+        })
+    }
+
+    /// Runs on `stack` instead of a fresh one, reusing its allocation.
+    pub fn with_stack(mut self, stack: Stack) -> Self {
+        self.processor = Processor::with_stack(stack);
+        self
+    }
+
+    /// Gives back the operand stack once the execution is over.
+    pub fn into_stack(self) -> Stack {
+        self.processor.into_stack()
+    }
+
+    /// Index of the function being executed.
+    pub fn func(&self) -> usize {
+        self.func
+    }
+
+    /// Runs at most `fuel` instructions of `code`, or until it returns when
+    /// `fuel` is `None`. `Poll::Pending` means it can be resumed.
+    pub fn resume(
+        &mut self,
+        code: &Code,
+        config: &mut Config,
+        fuel: Option<u64>,
+    ) -> Result<Poll<i32>, RuntimeError> {
+        let info = FuncInfo {
+            index: self.func,
+            name: Some(&self.name),
+        };
+        let interrupt = &config.interrupt;
+        let hooks = &mut config.hooks;
+        let hooked = !hooks.is_empty();
+        if !self.started {
+            self.started = true;
+            self.processor.reserve(code.max_stack);
+            // Declared locals start out as zero.
+            self.locals = self.params.clone();
+            self.locals
+                .resize(self.params.len() + code.func.locals.len(), 0);
+            for hook in hooks.iter_mut() {
+                hook.on_call_enter(info, &self.params);
+            }
+        }
+
+        let mut on_instr = |pc: usize, stack: &[i32], locals: &[i32]| {
+            let step = Step {
+                func: info,
+                pc,
+                offset: code.offsets[pc],
+                instr: &code.func.body[pc],
+                stack,
+                locals,
+            };
+            hooks.iter_mut().try_for_each(|h| h.on_instruction(&step))
+        };
+        let on_instr: Option<OnInstr> = if hooked { Some(&mut on_instr) } else { None };
+        let result =
+            match self
+                .processor
+                .execute_func(&code.ops, &self.locals, fuel, interrupt, on_instr)
+            {
+                Ok(false) => return Ok(Poll::Pending),
+                Ok(true) => self.processor.get_result(),
+                Err(kind) => Err(kind),
+            };
 
-    let EDesc::FuncExport(f_index) = export.e_desc;
-    let func = &ast.funcs[f_index];
-    let f_type = &ast.types[func.f_type as usize];
+        self.finish(code, config, result).map(Poll::Ready)
+    }
 
-    if f_type.0.len() != params.len() {
-        return Err(RuntimeError::InvalidArgNumber);
-    };
+    /// Stops a paused execution with the given trap.
+    pub fn abort(&mut self, code: &Code, config: &mut Config, kind: TrapKind) -> RuntimeError {
+        match self.finish(code, config, Err(kind)) {
+            Ok(_) => unreachable!("aborted executions always trap"),
+            Err(err) => err,
+        }
+    }
 
-    let mut processor = Processor::new();
-    processor.execute_func(&func, params);
+    fn finish(
+        &mut self,
+        code: &Code,
+        config: &mut Config,
+        result: Result<i32, TrapKind>,
+    ) -> Result<i32, RuntimeError> {
+        let info = FuncInfo {
+            index: self.func,
+            name: Some(&self.name),
+        };
+        let pc = self.processor.pc();
+        if let (Err(kind), false) = (&result, config.hooks.is_empty()) {
+            let stack = self.processor.stack_values();
+            let state = TrapState {
+                func: info,
+                kind,
+                offset: code.offsets[pc],
+                instr: code.func.body.get(pc),
+                stack: &stack,
+                locals: &self.locals,
+            };
+            for hook in config.hooks.iter_mut() {
+                hook.on_trap(&state);
+            }
+        }
+        for hook in config.hooks.iter_mut() {
+            hook.on_call_exit(info, &result);
+        }
 
-    Ok(processor.get_result())
+        result.map_err(|kind| {
+            RuntimeError::Trap(Trap {
+                kind,
+                backtrace: vec![Frame {
+                    func: self.func,
+                    name: Some(self.name.clone()),
+                    offset: code.offsets[pc],
+                }],
+            })
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::runtime::disassembler::CodeMap;
+    use crate::runtime::hooks::Hooks;
+    use crate::runtime::ir;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Clone, Default)]
+    struct Recorder(Rc<RefCell<Vec<String>>>);
+
+    impl Hooks for Recorder {
+        fn on_call_enter(&mut self, func: FuncInfo, params: &[i32]) {
+            let event = format!("enter {:?} {:?}", func.name, params);
+            self.0.borrow_mut().push(event);
+        }
+
+        fn on_call_exit(&mut self, func: FuncInfo, result: &Result<i32, TrapKind>) {
+            let event = format!("exit {:?} {:?}", func.name, result);
+            self.0.borrow_mut().push(event);
+        }
+
+        fn on_instruction(&mut self, step: &Step) -> Result<(), TrapKind> {
+            let event = format!("{:#x} {} {:?}", step.offset, step.instr, step.stack);
+            self.0.borrow_mut().push(event);
+            Ok(())
+        }
+    }
+
+    fn compile(func: &Func, offsets: &[usize]) -> Code {
+        Code {
+            func: func.clone(),
+            ops: ir::compile(&func.body),
+            max_stack: ir::max_stack(&func.body),
+            offsets: offsets.to_vec(),
+        }
+    }
+
+    fn invoke_function(
+        ast: &Module,
+        code_map: &CodeMap,
+        func: &str,
+        params: &[i32],
+        config: &mut Config,
+    ) -> Result<i32, RuntimeError> {
+        let export = ast.exports.iter().find(|e| e.name == func).unwrap();
+        let EDesc::FuncExport(index) = export.e_desc;
+        let code = compile(&ast.funcs[index], &code_map[index]);
+        let f_type = &ast.types[code.func.f_type as usize];
+        let mut execution = Execution::new(index, func, f_type, params)?;
+        match execution.resume(&code, config, None)? {
+            Poll::Ready(result) => Ok(result),
+            Poll::Pending => unreachable!("execution without a fuel limit ran to completion"),
+        }
+    }
 
     #[test]
     fn invoke_function_test() {
@@ -42,8 +233,185 @@ mod tests {
             }],
         };
 
-        let result = invoke_function(&ast, "add", &[5, 6]).unwrap();
+        let result = invoke_function(
+            &ast,
+            &vec![vec![35, 37, 39, 40]],
+            "add",
+            &[5, 6],
+            &mut Config::default(),
+        )
+        .unwrap();
 
         assert_eq!(11, result);
     }
+
+    #[test]
+    fn declared_locals_start_at_zero() {
+        let ast = Module {
+            types: vec![(vec![ValueType::I32], vec![ValueType::I32])],
+            funcs: vec![Func {
+                f_type: 0,
+                locals: vec![ValueType::I32],
+                body: vec![Instr::LocalGet(1), Instr::LocalGet(0), Instr::I32Add],
+            }],
+            exports: vec![Export {
+                name: "inc".to_string(),
+                e_desc: EDesc::FuncExport(0),
+            }],
+        };
+
+        let result = invoke_function(
+            &ast,
+            &vec![vec![37, 39, 41, 42]],
+            "inc",
+            &[5],
+            &mut Config::default(),
+        );
+
+        assert_eq!(Ok(5), result);
+    }
+
+    #[test]
+    fn i32_add_wraps_on_overflow() {
+        let ast = Module {
+            types: vec![(vec![ValueType::I32, ValueType::I32], vec![ValueType::I32])],
+            funcs: vec![Func {
+                f_type: 0,
+                locals: vec![],
+                body: vec![Instr::LocalGet(0), Instr::LocalGet(1), Instr::I32Add],
+            }],
+            exports: vec![Export {
+                name: "add".to_string(),
+                e_desc: EDesc::FuncExport(0),
+            }],
+        };
+
+        let result = invoke_function(
+            &ast,
+            &vec![vec![35, 37, 39, 40]],
+            "add",
+            &[i32::MAX, 1],
+            &mut Config::default(),
+        )
+        .unwrap();
+
+        assert_eq!(i32::MIN, result);
+    }
+
+    #[test]
+    fn trap_reports_backtrace() {
+        let ast = Module {
+            types: vec![(vec![ValueType::I32], vec![ValueType::I32])],
+            funcs: vec![Func {
+                f_type: 0,
+                locals: vec![],
+                body: vec![Instr::LocalGet(0), Instr::I32Add],
+            }],
+            exports: vec![Export {
+                name: "broken".to_string(),
+                e_desc: EDesc::FuncExport(0),
+            }],
+        };
+
+        let result = invoke_function(
+            &ast,
+            &vec![vec![35, 37, 38]],
+            "broken",
+            &[1],
+            &mut Config::default(),
+        );
+
+        assert_eq!(
+            Err(RuntimeError::Trap(Trap {
+                kind: TrapKind::StackUnderflow,
+                backtrace: vec![Frame {
+                    func: 0,
+                    name: Some("broken".to_string()),
+                    offset: 37,
+                }],
+            })),
+            result
+        );
+    }
+
+    #[test]
+    fn hooks_observe_call_and_instructions() {
+        let ast = Module {
+            types: vec![(vec![ValueType::I32, ValueType::I32], vec![ValueType::I32])],
+            funcs: vec![Func {
+                f_type: 0,
+                locals: vec![],
+                body: vec![Instr::LocalGet(0), Instr::LocalGet(1), Instr::I32Add],
+            }],
+            exports: vec![Export {
+                name: "add".to_string(),
+                e_desc: EDesc::FuncExport(0),
+            }],
+        };
+        let recorder = Recorder::default();
+        let mut config = Config::default();
+        config.hooks.push(Box::new(recorder.clone()));
+
+        let code_map = vec![vec![35, 37, 39, 40]];
+        invoke_function(&ast, &code_map, "add", &[2, 3], &mut config).unwrap();
+
+        assert_eq!(
+            vec![
+                "enter Some(\"add\") [2, 3]",
+                "0x23 local.get 0 []",
+                "0x25 local.get 1 [2]",
+                "0x27 i32.add [2, 3]",
+                "exit Some(\"add\") Ok(5)",
+            ],
+            *recorder.0.borrow()
+        );
+    }
+
+    #[test]
+    fn execution_resumes_after_running_out_of_fuel() {
+        let ast = Module {
+            types: vec![(vec![ValueType::I32, ValueType::I32], vec![ValueType::I32])],
+            funcs: vec![Func {
+                f_type: 0,
+                locals: vec![],
+                body: vec![Instr::LocalGet(0), Instr::LocalGet(1), Instr::I32Add],
+            }],
+            exports: vec![Export {
+                name: "add".to_string(),
+                e_desc: EDesc::FuncExport(0),
+            }],
+        };
+        let code = compile(&ast.funcs[0], &[35, 37, 39, 40]);
+        let mut config = Config::default();
+        let mut execution = Execution::new(0, "add", &ast.types[0], &[2, 3]).unwrap();
+
+        let mut resume = || execution.resume(&code, &mut config, Some(2));
+
+        assert_eq!(Ok(Poll::Pending), resume());
+        assert_eq!(Ok(Poll::Ready(5)), resume());
+    }
+
+    #[test]
+    fn fused_add_and_its_fallback_agree() {
+        let func = Func {
+            f_type: 0,
+            locals: vec![],
+            body: vec![Instr::LocalGet(0), Instr::LocalGet(1), Instr::I32Add],
+        };
+        let f_type = (vec![ValueType::I32, ValueType::I32], vec![ValueType::I32]);
+        let code = compile(&func, &[35, 37, 39, 40]);
+        let mut config = Config::default();
+
+        for fuel in [3, 1] {
+            let mut execution = Execution::new(0, "add", &f_type, &[i32::MAX, 1]).unwrap();
+            let result = loop {
+                if let Poll::Ready(result) =
+                    execution.resume(&code, &mut config, Some(fuel)).unwrap()
+                {
+                    break result;
+                }
+            };
+            assert_eq!(i32::MIN, result);
+        }
+    }
 }
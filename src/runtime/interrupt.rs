@@ -0,0 +1,43 @@
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Stops a running guest at its next instruction. Clones share the same
+/// flag, and setting it only touches an atomic, so it is safe to call
+/// from other threads and from signal handlers.
+#[derive(Clone, Default)]
+pub struct InterruptHandle {
+    flag: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests that the current, or else the next, execution stops with
+    /// an `Interrupted` trap.
+    pub fn interrupt(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    /// Consumes a pending request, so the instance can run again afterwards.
+    pub fn take(&self) -> bool {
+        self.flag.load(Ordering::Relaxed) && self.flag.swap(false, Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn take_consumes_request_from_any_clone() {
+        let handle = InterruptHandle::new();
+        let clone = handle.clone();
+        assert!(!handle.take());
+
+        clone.interrupt();
+        assert!(handle.take());
+        assert!(!handle.take());
+    }
+}
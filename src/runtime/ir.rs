@@ -0,0 +1,101 @@
+use crate::ast::Instr;
+use alloc::vec::Vec;
+
+/// The interpreter's internal instruction set. Function bodies are
+/// translated into it once, so execution does not depend on how the
+/// instructions were encoded.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Op {
+    LocalGet(u32),
+    I32Add,
+    /// `local.get a; local.get b; i32.add`. The two ops after it are left
+    /// in place, so the processor can fall back to running them one by one
+    /// and every instruction keeps its own index.
+    AddLocals(u32, u32),
+}
+
+impl Op {
+    /// Number of instructions a fused op stands for.
+    pub fn width(&self) -> usize {
+        match self {
+            Op::AddLocals(..) => 3,
+            _ => 1,
+        }
+    }
+}
+
+pub fn compile(body: &[Instr]) -> Vec<Op> {
+    let mut ops: Vec<Op> = body
+        .iter()
+        .map(|instr| match instr {
+            Instr::LocalGet(i) => Op::LocalGet(*i as u32),
+            Instr::I32Add => Op::I32Add,
+        })
+        .collect();
+    fuse(&mut ops);
+    ops
+}
+
+/// The deepest the operand stack gets while running `body`.
+pub fn max_stack(body: &[Instr]) -> usize {
+    let mut depth: usize = 0;
+    let mut max = 0;
+    for instr in body {
+        match instr {
+            Instr::LocalGet(_) => depth += 1,
+            Instr::I32Add => depth = depth.saturating_sub(1),
+        }
+        max = max.max(depth);
+    }
+    max
+}
+
+/// Replaces the first op of common sequences with a superinstruction.
+fn fuse(ops: &mut [Op]) {
+    for i in 0..ops.len() {
+        if let [Op::LocalGet(a), Op::LocalGet(b), Op::I32Add, ..] = ops[i..] {
+            ops[i] = Op::AddLocals(a, b);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compile_fuses_add_of_locals() {
+        assert_eq!(
+            vec![Op::AddLocals(0, 1), Op::LocalGet(1), Op::I32Add],
+            compile(&[Instr::LocalGet(0), Instr::LocalGet(1), Instr::I32Add])
+        );
+    }
+
+    #[test]
+    fn compile_leaves_other_sequences() {
+        let body = [Instr::LocalGet(0), Instr::I32Add, Instr::LocalGet(1)];
+
+        assert_eq!(
+            vec![Op::LocalGet(0), Op::I32Add, Op::LocalGet(1)],
+            compile(&body)
+        );
+    }
+
+    #[test]
+    fn max_stack_of_add() {
+        let body = [
+            Instr::LocalGet(0),
+            Instr::LocalGet(1),
+            Instr::I32Add,
+            Instr::LocalGet(0),
+            Instr::I32Add,
+        ];
+
+        assert_eq!(2, max_stack(&body));
+    }
+
+    #[test]
+    fn ops_are_compact() {
+        assert_eq!(12, std::mem::size_of::<Op>());
+    }
+}
@@ -1,18 +1,81 @@
-use crate::runtime::disassembler::parse_binary;
-use crate::runtime::error::RuntimeError;
+use crate::ast::Module;
+use crate::runtime::disassembler::{parse_binary, CodeMap};
 use crate::runtime::reader::Reader;
+use alloc::vec::Vec;
 
+#[cfg(feature = "std")]
+pub use crate::runtime::bench::bench;
+pub use crate::runtime::config::Config;
+pub use crate::runtime::coverage::Coverage;
+#[cfg(feature = "std")]
+pub use crate::runtime::debugger::Debugger;
+pub use crate::runtime::diff::{diff, Change};
+pub use crate::runtime::error::RuntimeError;
+pub use crate::runtime::graph::{call_graph, call_graph_dot, cfg_dot, find_func};
+pub use crate::runtime::info::{info, Info};
+pub use crate::runtime::instance::Instance;
+pub use crate::runtime::module::CompiledModule;
+#[cfg(feature = "std")]
+pub use crate::runtime::profiler::Profiler;
+pub use crate::runtime::stats::stats;
+#[cfg(feature = "std")]
+pub use crate::runtime::trace::Tracer;
+pub use crate::runtime::trap::{Frame, Trap, TrapKind};
+pub use crate::runtime::validator::{validate, ValidationError};
+pub use crate::runtime::value::Value;
+
+#[cfg(feature = "std")]
+mod bench;
+#[cfg(feature = "std")]
+pub mod cache;
+mod config;
+pub mod coredump;
+mod coverage;
+#[cfg(feature = "std")]
+mod debugger;
+mod diff;
 mod disassembler;
 mod error;
+mod graph;
+pub mod hooks;
+mod info;
+mod instance;
 mod interpreter;
+pub mod interrupt;
+mod ir;
+mod module;
+#[cfg(not(feature = "std"))]
+mod once;
+#[cfg(feature = "std")]
+pub mod pool;
 mod processor;
+#[cfg(feature = "std")]
+mod profiler;
 mod reader;
+pub mod scheduler;
+#[cfg(feature = "sign")]
+pub mod signature;
 mod stack;
+mod stats;
+#[cfg(test)]
+mod testing;
+#[cfg(feature = "std")]
+mod trace;
+mod trap;
+mod validator;
+mod value;
 
-pub fn invoke_function(binary: Vec<u8>, f_name: &str, params: &[i32]) -> Result<i32, RuntimeError> {
-    let reader = Reader::new(binary);
-    let ast = parse_binary(&reader)?;
-    interpreter::invoke_function(&ast, f_name, params)
+pub fn disassemble(binary: Vec<u8>) -> Result<(Module, CodeMap), RuntimeError> {
+    parse_binary(&Reader::new(binary))
+}
+
+pub fn invoke_function(
+    binary: Vec<u8>,
+    f_name: &str,
+    params: &[i32],
+    config: Config,
+) -> Result<i32, RuntimeError> {
+    Instance::new(binary, config)?.invoke(f_name, params)
 }
 
 #[cfg(test)]
@@ -57,8 +120,7 @@ mod tests {
             0x61, // a
             0x64, // d
             0x64, // d
-            0x00, // 0
-            // export kind
+            0x00, // export kind
             0x00, // export func index
             // section "Code" (10)
             0x0a, // section code
@@ -75,7 +137,7 @@ mod tests {
             0x0b, // end
         ];
 
-        let result = invoke_function(binary, "add", &[1, 4]).unwrap();
+        let result = invoke_function(binary, "add", &[1, 4], Config::default()).unwrap();
 
         assert_eq!(5, result);
     }
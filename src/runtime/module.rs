@@ -0,0 +1,240 @@
+use crate::ast::{EDesc, Export, ExportedFunc, Func, Type};
+use crate::runtime::config::Config;
+use crate::runtime::disassembler::{parse_binary_lazy, parse_body};
+use crate::runtime::error::RuntimeError;
+use crate::runtime::ir::{self, Op};
+#[cfg(not(feature = "std"))]
+use crate::runtime::once::OnceBox as OnceLock;
+use crate::runtime::reader::Reader;
+#[cfg(feature = "sign")]
+use crate::runtime::signature::{self, VerifyingKey};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ops::Range;
+#[cfg(feature = "sign")]
+use std::sync::Mutex;
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+
+/// A function body ready for execution.
+pub struct Code {
+    /// The decoded function, as written in the module.
+    pub func: Func,
+    /// The body translated to the interpreter's instruction set.
+    pub ops: Vec<Op>,
+    /// Byte offset of every instruction, including the trailing `end`.
+    pub offsets: Vec<usize>,
+    /// The most values the body keeps on the operand stack at once.
+    pub max_stack: usize,
+}
+
+/// A module prepared for execution and shared by all of its instances,
+/// which may run on different threads. Each function body is decoded and
+/// compiled the first time it is needed and cached afterwards.
+pub struct CompiledModule {
+    pub types: Vec<Type>,
+    pub exports: Vec<Export>,
+    f_types: Vec<i32>,
+    binary: Arc<[u8]>,
+    bodies: Vec<Range<usize>>,
+    code: Vec<OnceLock<Code>>,
+    /// Keys the binary's signature has already been checked against.
+    #[cfg(feature = "sign")]
+    verified: Mutex<Vec<VerifyingKey>>,
+}
+
+impl CompiledModule {
+    pub fn new(binary: Vec<u8>) -> Result<Self, RuntimeError> {
+        parse_binary_lazy(Reader::new(binary))
+    }
+
+    pub fn from_sections(
+        types: Vec<Type>,
+        exports: Vec<Export>,
+        f_types: Vec<i32>,
+        binary: Arc<[u8]>,
+        bodies: Vec<Range<usize>>,
+    ) -> Self {
+        let code = bodies.iter().map(|_| OnceLock::new()).collect();
+        Self {
+            types,
+            exports,
+            f_types,
+            binary,
+            bodies,
+            code,
+            #[cfg(feature = "sign")]
+            verified: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Checks the binary's signature if `config` requires one. Each key is
+    /// only checked once, however many instances are made.
+    pub(crate) fn check_signature(&self, _config: &Config) -> Result<(), RuntimeError> {
+        #[cfg(feature = "sign")]
+        if let Some(key) = &_config.verifying_key {
+            // Keys are only ever added, so a panic while it was locked
+            // cannot have left the list inconsistent.
+            let mut verified = self.verified.lock().unwrap_or_else(|e| e.into_inner());
+            if !verified.contains(key) {
+                signature::verify(&self.binary, key)?;
+                verified.push(*key);
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds the function exported as `name`, returning its index and type.
+    pub fn export_func(&self, name: &str) -> Result<(usize, &Type), RuntimeError> {
+        let export = match self.exports.iter().find(|e| e.name == name) {
+            None => return Err(RuntimeError::ExportNotFound(name.into())),
+            Some(e) => e,
+        };
+
+        let EDesc::FuncExport(index) = export.e_desc;
+        let f_type = self.func_type(index).ok_or(RuntimeError::InvalidExport)?;
+        Ok((index, f_type))
+    }
+
+    /// The bytes the module was decoded from.
+    #[cfg(feature = "std")]
+    pub(crate) fn binary(&self) -> &[u8] {
+        &self.binary
+    }
+
+    /// The type index of each function.
+    #[cfg(feature = "std")]
+    pub(crate) fn f_types(&self) -> &[i32] {
+        &self.f_types
+    }
+
+    /// The bytes each function body takes in the binary.
+    #[cfg(feature = "std")]
+    pub(crate) fn bodies(&self) -> &[Range<usize>] {
+        &self.bodies
+    }
+
+    /// The number of functions in the module.
+    pub fn func_count(&self) -> usize {
+        self.bodies.len()
+    }
+
+    /// The type of the function at `index`, read without decoding its body.
+    pub fn func_type(&self, index: usize) -> Option<&Type> {
+        let f_type = *self.f_types.get(index)?;
+        self.types.get(f_type as usize)
+    }
+
+    /// Every function export, so an embedder can check what a module
+    /// offers before instantiating it.
+    pub fn exported_funcs(&self) -> Vec<ExportedFunc<'_>> {
+        self.exports
+            .iter()
+            .map(|export| {
+                let EDesc::FuncExport(index) = export.e_desc;
+                ExportedFunc {
+                    name: &export.name,
+                    index,
+                    ty: self.func_type(index),
+                }
+            })
+            .collect()
+    }
+
+    /// The code of the function at `index`.
+    pub fn code(&self, index: usize) -> Result<&Code, RuntimeError> {
+        let cell = &self.code[index];
+        if cell.get().is_none() {
+            let range = &self.bodies[index];
+            log::debug!("compiling func[{}] at {:#x}", index, range.start);
+            let reader = Reader::shared(self.binary.clone());
+            reader.seek(range.start);
+            let (locals, body, offsets) = parse_body(&reader, index, range.len())?;
+            let code = Code {
+                ops: ir::compile(&body),
+                max_stack: ir::max_stack(&body),
+                func: Func {
+                    f_type: self.f_types[index],
+                    locals,
+                    body,
+                },
+                offsets,
+            };
+            // Another thread may have decoded it meanwhile; both are equal.
+            let _ = cell.set(code);
+        }
+
+        Ok(cell.get().expect("body was decoded above"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Instr;
+
+    #[test]
+    fn bodies_are_decoded_on_first_use() {
+        let binary = vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x07, 0x01, 0x60, 0x02, 0x7f, 0x7f, 0x01, 0x7f, // type section
+            0x03, 0x03, 0x02, 0x00, 0x00, // function section
+            0x07, 0x07, 0x01, 0x03, 0x61, 0x64, 0x64, 0x00, 0x00, // export section
+            0x0a, 0x0d, 0x02, // code section, two functions
+            0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6a, 0x0b, // add
+            0x02, 0x00, 0xff, // invalid body
+        ];
+
+        let module = CompiledModule::new(binary).unwrap();
+        let code = module.code(0).unwrap();
+
+        assert_eq!(
+            vec![Instr::LocalGet(0), Instr::LocalGet(1), Instr::I32Add],
+            code.func.body
+        );
+        assert_eq!(
+            vec![Op::AddLocals(0, 1), Op::LocalGet(1), Op::I32Add],
+            code.ops
+        );
+        assert_eq!(vec![36, 38, 40, 41], code.offsets);
+        assert!(std::ptr::eq(code, module.code(0).unwrap()));
+        assert_eq!(
+            vec![ExportedFunc {
+                name: "add",
+                index: 0,
+                ty: Some(&module.types[0]),
+            }],
+            module.exported_funcs()
+        );
+        assert_eq!(
+            Err(RuntimeError::InvalidInstruction {
+                func: 1,
+                offset: 44
+            }),
+            module.code(1).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn exports_of_missing_functions_are_rejected() {
+        let binary = vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x07, 0x07, 0x01, 0x03, 0x61, 0x64, 0x64, 0x00, 0x05, // export section
+        ];
+
+        let module = CompiledModule::new(binary).unwrap();
+
+        assert_eq!(
+            Err(RuntimeError::InvalidExport),
+            module.export_func("add").map(|_| ())
+        );
+    }
+
+    #[test]
+    fn compiled_module_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<CompiledModule>();
+    }
+}
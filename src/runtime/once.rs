@@ -0,0 +1,72 @@
+//! A stand-in for `std::sync::OnceLock` where there is no `std`, so that
+//! modules can still share lazily decoded bodies between instances.
+
+use alloc::boxed::Box;
+use core::marker::PhantomData;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+/// A cell set at most once. Threads that race to set it each build a
+/// value, and the first one stored wins.
+pub struct OnceBox<T> {
+    ptr: AtomicPtr<T>,
+    _owns: PhantomData<*mut T>,
+}
+
+// SAFETY: the cell owns a `T` and hands out shared references to it, like
+// `OnceLock<T>`, which is `Send` and `Sync` under the same bounds.
+unsafe impl<T: Send> Send for OnceBox<T> {}
+unsafe impl<T: Send + Sync> Sync for OnceBox<T> {}
+
+impl<T> OnceBox<T> {
+    pub const fn new() -> Self {
+        Self {
+            ptr: AtomicPtr::new(ptr::null_mut()),
+            _owns: PhantomData,
+        }
+    }
+
+    pub fn get(&self) -> Option<&T> {
+        // SAFETY: a non-null pointer comes from `Box::into_raw` in `set`,
+        // and is only freed when the cell is dropped.
+        unsafe { self.ptr.load(Ordering::Acquire).as_ref() }
+    }
+
+    /// Stores `value`, or gives it back if the cell is already set.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        let new = Box::into_raw(Box::new(value));
+        match self
+            .ptr
+            .compare_exchange(ptr::null_mut(), new, Ordering::AcqRel, Ordering::Acquire)
+        {
+            Ok(_) => Ok(()),
+            // SAFETY: `new` was never shared, so this is its only owner.
+            Err(_) => Err(*unsafe { Box::from_raw(new) }),
+        }
+    }
+}
+
+impl<T> Drop for OnceBox<T> {
+    fn drop(&mut self) {
+        let ptr = *self.ptr.get_mut();
+        if !ptr.is_null() {
+            // SAFETY: as in `get`; no references outlive `&mut self`.
+            drop(unsafe { Box::from_raw(ptr) });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_first_value_stays() {
+        let cell = OnceBox::new();
+
+        assert_eq!(None, cell.get());
+        assert_eq!(Ok(()), cell.set(vec![1]));
+        assert_eq!(Err(vec![2]), cell.set(vec![2]));
+        assert_eq!(Some(&vec![1]), cell.get());
+    }
+}
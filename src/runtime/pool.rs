@@ -0,0 +1,201 @@
+use crate::runtime::config::Config;
+use crate::runtime::error::RuntimeError;
+use crate::runtime::instance::Instance;
+use crate::runtime::module::CompiledModule;
+use crate::runtime::stack::Stack;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// A fixed number of instances of one module, compiled once up front and
+/// handed out for reuse. The pool can be shared between threads; each
+/// instance is built on the thread that acquires it, with a fresh config,
+/// and reuses the operand stack of the instance released before it. The
+/// stacks start out large enough for the module's deepest function.
+pub struct InstancePool {
+    module: Arc<CompiledModule>,
+    config: Box<dyn Fn() -> Config + Send + Sync>,
+    free: Mutex<Vec<Stack>>,
+}
+
+impl InstancePool {
+    /// Compiles `binary` for `size` instances, each configured by a call
+    /// to `config`. Fails if a function body does not decode or the config
+    /// requires a signature the module lacks.
+    pub fn new(
+        binary: Vec<u8>,
+        size: usize,
+        config: impl Fn() -> Config + Send + Sync + 'static,
+    ) -> Result<Self, RuntimeError> {
+        let module = Arc::new(CompiledModule::new(binary)?);
+        module.check_signature(&config())?;
+        let depth = max_stack(&module)?;
+        let free = (0..size)
+            .map(|_| Stack::with_capacity::<i32>(depth))
+            .collect();
+
+        Ok(Self {
+            module,
+            config: Box::new(config),
+            free: Mutex::new(free),
+        })
+    }
+
+    /// Takes a free instance, or `None` when all of them are in use. The
+    /// instance goes back to the pool when the returned guard is dropped.
+    /// Fails if the config made for it requires a signature the module
+    /// lacks.
+    pub fn acquire(&self) -> Result<Option<PooledInstance<'_>>, RuntimeError> {
+        let stack = match self.free().pop() {
+            Some(stack) => stack,
+            None => return Ok(None),
+        };
+        match Instance::from_module(self.module.clone(), (self.config)()) {
+            Ok(instance) => Ok(Some(PooledInstance {
+                pool: self,
+                instance: Some(instance.with_stack(stack)),
+            })),
+            Err(err) => {
+                self.free().push(stack);
+                Err(err)
+            }
+        }
+    }
+
+    pub fn available(&self) -> usize {
+        self.free().len()
+    }
+
+    fn free(&self) -> MutexGuard<'_, Vec<Stack>> {
+        // The list is only pushed to and popped from, so a panic while it
+        // was locked cannot have left it inconsistent.
+        self.free.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The most values any function of `module` keeps on the stack at once.
+fn max_stack(module: &CompiledModule) -> Result<usize, RuntimeError> {
+    let mut depth = 0;
+    for index in 0..module.func_count() {
+        depth = depth.max(module.code(index)?.max_stack);
+    }
+    Ok(depth)
+}
+
+pub struct PooledInstance<'a> {
+    pool: &'a InstancePool,
+    instance: Option<Instance>,
+}
+
+impl Deref for PooledInstance<'_> {
+    type Target = Instance;
+
+    fn deref(&self) -> &Instance {
+        self.instance
+            .as_ref()
+            .expect("instance is only taken on drop")
+    }
+}
+
+impl DerefMut for PooledInstance<'_> {
+    fn deref_mut(&mut self) -> &mut Instance {
+        self.instance
+            .as_mut()
+            .expect("instance is only taken on drop")
+    }
+}
+
+impl Drop for PooledInstance<'_> {
+    fn drop(&mut self) {
+        if let Some(instance) = self.instance.take() {
+            self.pool.free().push(instance.into_stack());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::testing::add_binary;
+    use std::thread;
+
+    #[test]
+    fn acquire_recycles_instances() {
+        let pool = InstancePool::new(add_binary(), 2, Config::default).unwrap();
+
+        let mut first = pool.acquire().unwrap().unwrap();
+        let second = pool.acquire().unwrap().unwrap();
+        assert!(pool.acquire().unwrap().is_none());
+        assert_eq!(Ok(3), first.invoke("add", &[1, 2]));
+
+        drop(first);
+        assert_eq!(1, pool.available());
+        assert_eq!(
+            Ok(7),
+            pool.acquire().unwrap().unwrap().invoke("add", &[3, 4])
+        );
+
+        drop(second);
+        assert_eq!(2, pool.available());
+    }
+
+    #[test]
+    fn stacks_are_sized_for_the_deepest_function() {
+        let pool = InstancePool::new(add_binary(), 2, Config::default).unwrap();
+
+        let mut free = pool.free();
+        // `add` pushes two i32s of four bytes each.
+        assert!(free.iter_mut().all(|stack| stack.capacity() >= 8));
+    }
+
+    #[test]
+    fn instances_use_the_pool_config() {
+        let pool = InstancePool::new(add_binary(), 1, || Config::new().with_fuel(2)).unwrap();
+
+        let result = pool.acquire().unwrap().unwrap().invoke("add", &[1, 2]);
+
+        assert!(matches!(result, Err(RuntimeError::Trap(_))));
+    }
+
+    #[test]
+    fn threads_share_one_pool() {
+        let pool = Arc::new(InstancePool::new(add_binary(), 4, Config::default).unwrap());
+
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let pool = pool.clone();
+                thread::spawn(move || {
+                    // Each thread holds one instance at a time, so there is
+                    // always one free.
+                    (0..100)
+                        .map(|j| {
+                            let mut instance = pool.acquire().unwrap().expect("instance is free");
+                            instance.invoke("add", &[i, j]).unwrap()
+                        })
+                        .sum::<i32>()
+                })
+            })
+            .collect();
+        let sums: Vec<i32> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+
+        assert_eq!(vec![4950, 5050, 5150, 5250], sums);
+        assert_eq!(4, pool.available());
+    }
+
+    #[cfg(feature = "sign")]
+    #[test]
+    fn unsigned_modules_are_rejected_when_a_key_is_required() {
+        use crate::runtime::signature::{sign, SigningKey};
+
+        let key = SigningKey::from_bytes(&[7; 32]).verifying_key();
+        let config = move || Config::new().with_verifying_key(key);
+        let signing_key = SigningKey::from_bytes(&[7; 32]);
+
+        assert!(matches!(
+            InstancePool::new(add_binary(), 1, config),
+            Err(RuntimeError::Unsigned)
+        ));
+        let pool = InstancePool::new(sign(&add_binary(), &signing_key), 1, config).unwrap();
+        let result = pool.acquire().unwrap().unwrap().invoke("add", &[1, 2]);
+        assert_eq!(Ok(3), result);
+    }
+}
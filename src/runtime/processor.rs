@@ -1,34 +1,121 @@
-use crate::ast::{Func, Instr};
+use crate::runtime::interrupt::InterruptHandle;
+use crate::runtime::ir::Op;
 use crate::runtime::stack::Stack;
+use crate::runtime::trap::TrapKind;
+use alloc::vec::Vec;
+
+/// Called before each instruction with its index, the operand stack and
+/// the locals. Returning an error stops execution with that trap.
+pub type OnInstr<'a> = &'a mut dyn FnMut(usize, &[i32], &[i32]) -> Result<(), TrapKind>;
 
 pub struct Processor {
     stack: Stack,
+    pc: usize,
 }
 
 impl Processor {
     pub fn new() -> Self {
         Self {
             stack: Stack::new(),
+            pc: 0,
         }
     }
 
-    pub fn execute_func(&mut self, func: &Func, params: &[i32]) {
-        for instr in &func.body {
-            match instr {
-                Instr::LocalGet(i) => {
-                    self.stack.push(params[*i]);
+    pub fn with_stack(mut stack: Stack) -> Self {
+        stack.clear();
+        Self { stack, pc: 0 }
+    }
+
+    /// Gives back the operand stack so its allocation can be reused.
+    pub fn into_stack(self) -> Stack {
+        self.stack
+    }
+
+    /// Copies the operand stack, bottom first.
+    pub fn stack_values(&mut self) -> Vec<i32> {
+        self.stack.values()
+    }
+
+    pub fn reserve(&mut self, values: usize) {
+        self.stack.reserve::<i32>(values);
+    }
+
+    /// Index of the instruction currently executing; equals the body
+    /// length once the function has reached its `end`.
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// Executes `ops` from the current instruction on, stopping early once
+    /// `fuel` instructions have run. Returns whether the function finished.
+    ///
+    /// Fused ops only take their fast path when no hook needs to see each
+    /// instruction and enough fuel is left; otherwise they run as the first
+    /// instruction of their sequence.
+    pub fn execute_func(
+        &mut self,
+        ops: &[Op],
+        params: &[i32],
+        fuel: Option<u64>,
+        interrupt: &InterruptHandle,
+        mut on_instr: Option<OnInstr>,
+    ) -> Result<bool, TrapKind> {
+        let mut fuel = fuel;
+        while let Some(op) = ops.get(self.pc) {
+            if fuel == Some(0) {
+                return Ok(false);
+            }
+            if interrupt.take() {
+                return Err(TrapKind::Interrupted);
+            }
+            if let Some(on_instr) = on_instr.as_mut() {
+                on_instr(self.pc, &self.stack.values(), params)?;
+            } else if let Some(n) = self.execute_fused(op, params, fuel) {
+                self.pc += n;
+                fuel = fuel.map(|f| f - n as u64);
+                continue;
+            }
+            match *op {
+                Op::LocalGet(i) | Op::AddLocals(i, _) => {
+                    let i = i as usize;
+                    let local = params.get(i).ok_or(TrapKind::LocalOutOfBounds(i))?;
+                    self.stack.push(*local);
                 }
-                Instr::I32Add => {
-                    let a: i32 = self.stack.pop();
-                    let b: i32 = self.stack.pop();
-                    let result = a + b;
+                Op::I32Add => {
+                    let a: i32 = self.pop()?;
+                    let b: i32 = self.pop()?;
+                    let result = a.wrapping_add(b);
                     self.stack.push(result);
                 }
             }
+            self.pc += 1;
+            fuel = fuel.map(|f| f - 1);
+        }
+        Ok(true)
+    }
+
+    /// Runs a superinstruction in one go, returning how many instructions
+    /// it covered, or `None` if it has to be run step by step.
+    fn execute_fused(&mut self, op: &Op, params: &[i32], fuel: Option<u64>) -> Option<usize> {
+        if fuel.is_some_and(|f| f < op.width() as u64) {
+            return None;
+        }
+        match *op {
+            Op::AddLocals(a, b) => {
+                let a = params.get(a as usize)?;
+                let b = params.get(b as usize)?;
+                self.stack.push(a.wrapping_add(*b));
+            }
+            _ => return None,
         }
+        Some(op.width())
+    }
+
+    pub fn get_result(&mut self) -> Result<i32, TrapKind> {
+        self.pop()
     }
 
-    pub fn get_result(&mut self) -> i32 {
-        self.stack.pop::<i32>()
+    fn pop(&mut self) -> Result<i32, TrapKind> {
+        self.stack.pop().ok_or(TrapKind::StackUnderflow)
     }
 }
@@ -0,0 +1,152 @@
+use crate::runtime::hooks::{FuncInfo, Hooks, Step};
+use crate::runtime::trap::TrapKind;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+struct FuncProfile {
+    name: String,
+    calls: u64,
+    instrs: u64,
+    time: Duration,
+}
+
+struct Frame {
+    func: usize,
+    start: Instant,
+    instrs: u64,
+}
+
+/// Collects per-function call counts, executed instructions and wall time.
+#[derive(Default)]
+pub struct Profiler {
+    funcs: BTreeMap<usize, FuncProfile>,
+    frames: Vec<Frame>,
+    folded: BTreeMap<Vec<usize>, u64>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A table of all called functions, slowest first.
+    pub fn table(&self) -> String {
+        let mut funcs = self.funcs.values().collect::<Vec<&FuncProfile>>();
+        funcs.sort_by(|a, b| b.time.cmp(&a.time).then(a.name.cmp(&b.name)));
+        let names = funcs.iter().map(|f| f.name.len());
+        let width = names.chain(Some("function".len())).max().unwrap_or(0);
+
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            "{:<width$}  {:>8}  {:>10}  {:>12}",
+            "function", "calls", "instrs", "time"
+        );
+        for f in funcs {
+            let time = format!("{:?}", f.time);
+            let _ = write!(
+                out,
+                "\n{:<width$}  {:>8}  {:>10}  {:>12}",
+                f.name, f.calls, f.instrs, time
+            );
+        }
+        out
+    }
+
+    /// Call stacks in the folded format read by flamegraph tools, weighted
+    /// by the instructions executed in the innermost function.
+    pub fn folded(&self) -> String {
+        let mut out = String::new();
+        for (stack, instrs) in &self.folded {
+            let names = stack
+                .iter()
+                .map(|f| self.funcs[f].name.as_str())
+                .collect::<Vec<&str>>();
+            let _ = writeln!(out, "{} {}", names.join(";"), instrs);
+        }
+        out
+    }
+}
+
+impl Hooks for Profiler {
+    fn on_call_enter(&mut self, func: FuncInfo, _params: &[i32]) {
+        let profile = self.funcs.entry(func.index).or_default();
+        if profile.name.is_empty() {
+            profile.name = match func.name {
+                Some(name) => name.to_string(),
+                None => format!("func[{}]", func.index),
+            };
+        }
+        profile.calls += 1;
+
+        self.frames.push(Frame {
+            func: func.index,
+            start: Instant::now(),
+            instrs: 0,
+        });
+    }
+
+    fn on_call_exit(&mut self, _func: FuncInfo, _result: &Result<i32, TrapKind>) {
+        let stack = self.frames.iter().map(|f| f.func).collect::<Vec<usize>>();
+        if let Some(frame) = self.frames.pop() {
+            let profile = self.funcs.entry(frame.func).or_default();
+            profile.instrs += frame.instrs;
+            profile.time += frame.start.elapsed();
+            *self.folded.entry(stack).or_default() += frame.instrs;
+        }
+    }
+
+    fn on_instruction(&mut self, _step: &Step) -> Result<(), TrapKind> {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.instrs += 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Instr;
+
+    fn run(profiler: &mut Profiler, index: usize, name: Option<&str>, instrs: usize) {
+        let func = FuncInfo { index, name };
+        profiler.on_call_enter(func, &[]);
+        for pc in 0..instrs {
+            let step = Step {
+                func,
+                pc,
+                offset: 0,
+                instr: &Instr::I32Add,
+                stack: &[],
+                locals: &[],
+            };
+            profiler.on_instruction(&step).unwrap();
+        }
+        profiler.on_call_exit(func, &Ok(0));
+    }
+
+    #[test]
+    fn counts_calls_and_instructions() {
+        let mut profiler = Profiler::new();
+        run(&mut profiler, 0, Some("add"), 3);
+        run(&mut profiler, 0, Some("add"), 3);
+        run(&mut profiler, 1, None, 1);
+
+        assert_eq!("add 6\nfunc[1] 1\n", profiler.folded());
+
+        let table = profiler.table();
+        let rows = table.lines().collect::<Vec<&str>>();
+        assert_eq!(3, rows.len());
+        assert!(rows[0].starts_with("function     calls      instrs"));
+        // Rows are ordered by wall time, which varies between runs.
+        assert!(rows
+            .iter()
+            .any(|r| r.starts_with("add              2           6")));
+        assert!(rows
+            .iter()
+            .any(|r| r.starts_with("func[1]          1           1")));
+    }
+}
@@ -1,34 +1,125 @@
-use std::{cell::Cell, convert::TryInto};
+use crate::runtime::error::RuntimeError;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::{cell::Cell, convert::TryInto};
 
+#[derive(Clone)]
 pub struct Reader {
-    data: Vec<u8>,
+    data: Arc<[u8]>,
     pos: Cell<usize>,
 }
 
 impl Reader {
     pub fn new(data: Vec<u8>) -> Self {
+        Self::shared(data.into())
+    }
+
+    /// A reader over bytes that other readers may be using too.
+    pub fn shared(data: Arc<[u8]>) -> Self {
         Self {
             data,
             pos: Cell::new(0),
         }
     }
 
+    pub fn into_data(self) -> Arc<[u8]> {
+        self.data
+    }
+
     pub fn len(&self) -> usize {
         self.data.len()
     }
 
-    pub fn dword(&self) -> u32 {
-        let prev = self.pos.replace(self.pos.get() + 4);
-        u32::from_le_bytes(self.data[prev..self.pos.get()].try_into().unwrap())
+    pub fn pos(&self) -> usize {
+        self.pos.get()
+    }
+
+    pub fn seek(&self, pos: usize) {
+        self.pos.set(pos);
     }
 
-    pub fn bytes(&self, num: usize) -> &[u8] {
-        let prev = self.pos.replace(self.pos.get() + num);
-        &self.data[prev..self.pos.get()]
+    /// The next byte, without consuming it.
+    pub fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos.get()).copied()
     }
 
-    pub fn byte(&self) -> u8 {
-        let prev = self.pos.replace(self.pos.get() + 1);
-        self.data[prev]
+    pub fn dword(&self) -> Result<u32, RuntimeError> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// The next `num` bytes. Fails without moving if fewer are left.
+    pub fn bytes(&self, num: usize) -> Result<&[u8], RuntimeError> {
+        let start = self.pos.get();
+        let bytes = start
+            .checked_add(num)
+            .and_then(|end| self.data.get(start..end))
+            .ok_or(RuntimeError::UnexpectedEnd { offset: start })?;
+        let end = start + num;
+        self.pos.set(end);
+        Ok(bytes)
+    }
+
+    pub fn byte(&self) -> Result<u8, RuntimeError> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    /// An unsigned LEB128 number that fits in 32 bits.
+    pub fn var_u32(&self) -> Result<u32, RuntimeError> {
+        let offset = self.pos();
+        let mut value = 0;
+        for shift in (0..32).step_by(7) {
+            let byte = self
+                .byte()
+                .map_err(|_| RuntimeError::UnexpectedEnd { offset })?;
+            if shift == 28 && byte > 0x0f {
+                return Err(RuntimeError::IntegerTooLarge { offset });
+            }
+            value |= u32::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(RuntimeError::IntegerTooLarge { offset })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reading_past_the_end_fails() {
+        let reader = Reader::new(vec![1, 2, 3]);
+
+        assert_eq!(Ok(1), reader.byte());
+        assert_eq!(
+            Err(RuntimeError::UnexpectedEnd { offset: 1 }),
+            reader.dword()
+        );
+        assert_eq!(Ok(&[2, 3][..]), reader.bytes(2));
+        assert_eq!(
+            Err(RuntimeError::UnexpectedEnd { offset: 3 }),
+            reader.byte()
+        );
+        assert_eq!(3, reader.pos());
+    }
+
+    #[test]
+    fn var_u32_reads_leb128() {
+        let reader = Reader::new(vec![0x07, 0xe5, 0x8e, 0x26, 0xff, 0xff, 0xff, 0xff, 0x0f]);
+        let too_large = Reader::new(vec![0xff, 0xff, 0xff, 0xff, 0x1f]);
+
+        assert_eq!(Ok(7), reader.var_u32());
+        assert_eq!(Ok(624_485), reader.var_u32());
+        assert_eq!(Ok(u32::MAX), reader.var_u32());
+        assert_eq!(
+            Err(RuntimeError::IntegerTooLarge { offset: 0 }),
+            too_large.var_u32()
+        );
+        assert_eq!(
+            Err(RuntimeError::UnexpectedEnd { offset: 0 }),
+            Reader::new(vec![0x80]).var_u32()
+        );
     }
 }
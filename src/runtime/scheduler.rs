@@ -0,0 +1,157 @@
+use crate::runtime::error::RuntimeError;
+use crate::runtime::instance::Instance;
+use crate::runtime::interpreter::Execution;
+use crate::runtime::trap::TrapKind;
+use alloc::vec::Vec;
+use core::task::Poll;
+
+pub type TaskId = usize;
+
+struct Task {
+    instance: Instance,
+    execution: Execution,
+    /// Instructions the task may still execute; `None` is unlimited.
+    fuel: Option<u64>,
+    result: Option<Result<i32, RuntimeError>>,
+}
+
+/// Runs many instances cooperatively, giving each running task a slice
+/// of at most `slice` instructions per round.
+pub struct Scheduler {
+    tasks: Vec<Task>,
+    slice: u64,
+}
+
+impl Scheduler {
+    pub fn new(slice: u64) -> Self {
+        Self {
+            tasks: Vec::new(),
+            slice,
+        }
+    }
+
+    /// Queues an invocation of `func` on `instance`. A task that needs more
+    /// than `fuel` instructions fails with an out-of-fuel trap.
+    pub fn spawn(
+        &mut self,
+        mut instance: Instance,
+        func: &str,
+        params: &[i32],
+        fuel: Option<u64>,
+    ) -> Result<TaskId, RuntimeError> {
+        let execution = instance.start(func, params)?;
+        self.tasks.push(Task {
+            instance,
+            execution,
+            fuel,
+            result: None,
+        });
+        Ok(self.tasks.len() - 1)
+    }
+
+    /// Gives every unfinished task one slice. Returns whether any task is
+    /// still unfinished afterwards.
+    pub fn step(&mut self) -> bool {
+        let mut running = false;
+        let max = self.slice;
+        for task in self.tasks.iter_mut().filter(|t| t.result.is_none()) {
+            let slice = task.fuel.map_or(max, |f| f.min(max));
+            if slice == 0 {
+                let trap = task
+                    .instance
+                    .abort(&mut task.execution, TrapKind::OutOfFuel);
+                task.result = Some(Err(trap));
+                continue;
+            }
+
+            match task.instance.resume(&mut task.execution, Some(slice)) {
+                Ok(Poll::Ready(result)) => task.result = Some(Ok(result)),
+                Ok(Poll::Pending) => {
+                    task.fuel = task.fuel.map(|f| f - slice);
+                    running = true;
+                }
+                Err(err) => task.result = Some(Err(err)),
+            }
+        }
+        running
+    }
+
+    /// Runs all tasks to completion.
+    pub fn run(&mut self) {
+        while self.step() {}
+    }
+
+    /// The outcome of a task, once it has finished.
+    pub fn result(&self, task: TaskId) -> Option<&Result<i32, RuntimeError>> {
+        self.tasks.get(task).and_then(|t| t.result.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::config::Config;
+    use crate::runtime::hooks::{Hooks, Step};
+    use crate::runtime::testing::add_binary;
+    use crate::runtime::trap::Trap;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct Log(Rc<RefCell<Vec<(i32, usize)>>>);
+
+    impl Hooks for Log {
+        fn on_instruction(&mut self, step: &Step) -> Result<(), TrapKind> {
+            self.0.borrow_mut().push((step.locals[0], step.pc));
+            Ok(())
+        }
+    }
+
+    fn instance(log: &Rc<RefCell<Vec<(i32, usize)>>>) -> Instance {
+        let mut config = Config::default();
+        config.hooks.push(Box::new(Log(log.clone())));
+        Instance::new(add_binary(), config).unwrap()
+    }
+
+    #[test]
+    fn run_interleaves_tasks() {
+        let log = Rc::new(RefCell::new(vec![]));
+        let mut scheduler = Scheduler::new(2);
+        let a = scheduler
+            .spawn(instance(&log), "add", &[1, 2], None)
+            .unwrap();
+        let b = scheduler
+            .spawn(instance(&log), "add", &[10, 20], None)
+            .unwrap();
+
+        scheduler.run();
+
+        assert_eq!(Some(&Ok(3)), scheduler.result(a));
+        assert_eq!(Some(&Ok(30)), scheduler.result(b));
+        assert_eq!(
+            vec![(1, 0), (1, 1), (10, 0), (10, 1), (1, 2), (10, 2)],
+            *log.borrow()
+        );
+    }
+
+    #[test]
+    fn task_without_enough_fuel_traps() {
+        let log = Rc::new(RefCell::new(vec![]));
+        let mut scheduler = Scheduler::new(1);
+        let starved = scheduler
+            .spawn(instance(&log), "add", &[1, 2], Some(2))
+            .unwrap();
+        let fed = scheduler
+            .spawn(instance(&log), "add", &[1, 2], Some(3))
+            .unwrap();
+
+        scheduler.run();
+
+        match scheduler.result(starved) {
+            Some(Err(RuntimeError::Trap(Trap { kind, .. }))) => {
+                assert_eq!(&TrapKind::OutOfFuel, kind)
+            }
+            other => panic!("expected out-of-fuel trap, got {:?}", other),
+        }
+        assert_eq!(Some(&Ok(3)), scheduler.result(fed));
+    }
+}
@@ -0,0 +1,110 @@
+//! Ed25519 signatures over modules. A signed module ends with a custom
+//! section named `signature` holding the signature of every byte before it,
+//! so runtimes that know nothing of signatures still load it.
+//!
+//! Keys are read from PEM files, such as those made by
+//! `openssl genpkey -algorithm ed25519` and `openssl pkey -pubout`, through
+//! the re-exported `DecodePrivateKey` and `DecodePublicKey` traits.
+
+use crate::op_codes::section;
+use crate::runtime::error::RuntimeError;
+use ed25519_dalek::{Signature, Signer, SIGNATURE_LENGTH};
+
+pub use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey};
+pub use ed25519_dalek::{SigningKey, VerifyingKey};
+
+const NAME: &[u8] = b"signature";
+
+/// The custom section's header: id, size, and the length and bytes of the
+/// name. Every count here is below 128, so each takes one byte.
+fn header() -> Vec<u8> {
+    let size = 1 + NAME.len() + SIGNATURE_LENGTH;
+    [
+        vec![section::CUSTOM, size as u8, NAME.len() as u8],
+        NAME.to_vec(),
+    ]
+    .concat()
+}
+
+/// Splits a module into the signed bytes and the signature, if it ends
+/// with one.
+fn split(binary: &[u8]) -> (&[u8], Option<&[u8]>) {
+    let header = header();
+    let section_len = header.len() + SIGNATURE_LENGTH;
+    if let Some(start) = binary.len().checked_sub(section_len) {
+        let (signed, section) = binary.split_at(start);
+        if section.starts_with(&header) {
+            return (signed, Some(&section[header.len()..]));
+        }
+    }
+    (binary, None)
+}
+
+/// Signs `binary`, replacing any signature it already has.
+pub fn sign(binary: &[u8], key: &SigningKey) -> Vec<u8> {
+    let (signed, _) = split(binary);
+    let signature = key.sign(signed);
+    [signed, &header(), &signature.to_bytes()].concat()
+}
+
+/// Checks that `binary` carries a valid signature by `key`.
+pub fn verify(binary: &[u8], key: &VerifyingKey) -> Result<(), RuntimeError> {
+    let (signed, signature) = split(binary);
+    let signature = signature.ok_or(RuntimeError::Unsigned)?;
+    let signature = Signature::from_slice(signature).map_err(|_| RuntimeError::InvalidSignature)?;
+    key.verify_strict(signed, &signature)
+        .map_err(|_| RuntimeError::InvalidSignature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::testing::add_binary;
+    use crate::runtime::{disassemble, invoke_function, Config};
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    #[test]
+    fn signed_modules_verify_and_still_run() {
+        let signed = sign(&add_binary(), &key(1));
+
+        assert_eq!(Ok(()), verify(&signed, &key(1).verifying_key()));
+        assert_eq!(
+            disassemble(add_binary()).unwrap().0,
+            disassemble(signed.clone()).unwrap().0
+        );
+        assert_eq!(
+            Ok(3),
+            invoke_function(signed, "add", &[1, 2], Config::new())
+        );
+    }
+
+    #[test]
+    fn signing_again_replaces_the_signature() {
+        let signed = sign(&sign(&add_binary(), &key(1)), &key(2));
+
+        assert_eq!(add_binary().len() + header().len() + 64, signed.len());
+        assert_eq!(Ok(()), verify(&signed, &key(2).verifying_key()));
+    }
+
+    #[test]
+    fn tampered_and_unsigned_modules_are_rejected() {
+        let mut signed = sign(&add_binary(), &key(1));
+        signed[35] = 0x01;
+
+        assert_eq!(
+            Err(RuntimeError::InvalidSignature),
+            verify(&signed, &key(1).verifying_key())
+        );
+        assert_eq!(
+            Err(RuntimeError::InvalidSignature),
+            verify(&sign(&add_binary(), &key(2)), &key(1).verifying_key())
+        );
+        assert_eq!(
+            Err(RuntimeError::Unsigned),
+            verify(&add_binary(), &key(1).verifying_key())
+        );
+    }
+}
@@ -1,5 +1,7 @@
-use std::cell::Cell;
+use alloc::vec::Vec;
+use core::cell::Cell;
 
+#[derive(Default)]
 pub struct Stack {
     stack: Cell<Vec<u8>>,
 }
@@ -11,43 +13,100 @@ impl Stack {
         }
     }
 
+    /// An empty stack with room for `values` values of type `T`.
+    pub fn with_capacity<T: Stackable>(values: usize) -> Self {
+        let mut stack = Self::new();
+        stack.reserve::<T>(values);
+        stack
+    }
+
+    /// Makes room for at least `values` more values of type `T`.
+    pub fn reserve<T: Stackable>(&mut self, values: usize) {
+        self.stack.get_mut().reserve(values * T::byte_size());
+    }
+
+    /// The bytes the stack holds before it has to reallocate.
+    #[cfg(test)]
+    pub(crate) fn capacity(&mut self) -> usize {
+        self.stack.get_mut().capacity()
+    }
+
+    /// Drops all values, keeping the allocation for reuse.
+    pub fn clear(&mut self) {
+        self.stack.get_mut().clear();
+    }
+
     pub fn push<T: Stackable>(&mut self, arg: T) {
-        let mut bytes = arg.to_bytes();
-        self.stack.get_mut().append(&mut bytes);
+        self.stack.get_mut().extend_from_slice(&arg.to_bytes());
+    }
+
+    pub fn pop<T: Stackable>(&mut self) -> Option<T> {
+        use core::convert::TryInto;
+        let stack = self.stack.get_mut();
+        let at = stack.len().checked_sub(T::byte_size())?;
+        let value = T::from_bytes(stack[at..].try_into().unwrap());
+        stack.truncate(at);
+        Some(value)
     }
 
-    pub fn pop<T: Stackable>(&mut self) -> T {
-        use std::convert::TryInto;
-        let bytes = self
-            .stack
+    /// Copies the values on the stack, bottom first.
+    pub fn values<T: Stackable>(&mut self) -> Vec<T> {
+        use core::convert::TryInto;
+        self.stack
             .get_mut()
-            .iter()
-            .rev()
-            .take(T::byte_size())
-            .copied()
-            .collect::<Vec<u8>>();
-        let value = bytes.as_slice().try_into().unwrap();
-        self.stack.get_mut().truncate(T::byte_size());
-        T::from_bytes(value)
+            .chunks_exact(T::byte_size())
+            .map(|c| T::from_bytes(c.try_into().unwrap()))
+            .collect()
     }
 }
 
 pub trait Stackable {
-    fn to_bytes(&self) -> Vec<u8>;
+    fn to_bytes(&self) -> [u8; 4];
     fn from_bytes(stack: &[u8; 4]) -> Self;
     fn byte_size() -> usize;
 }
 
 impl Stackable for i32 {
-    fn to_bytes(&self) -> Vec<u8> {
-        self.to_ne_bytes().to_vec()
+    fn to_bytes(&self) -> [u8; 4] {
+        self.to_le_bytes()
     }
 
     fn from_bytes(stack: &[u8; 4]) -> Self {
-        i32::from_ne_bytes(*stack)
+        i32::from_le_bytes(*stack)
     }
 
     fn byte_size() -> usize {
         4
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pop_returns_values_in_lifo_order() {
+        let mut stack = Stack::new();
+        stack.push(1);
+        stack.push(-2);
+        stack.push(300);
+
+        assert_eq!(Some(300), stack.pop::<i32>());
+        assert_eq!(Some(-2), stack.pop::<i32>());
+        assert_eq!(Some(1), stack.pop::<i32>());
+        assert_eq!(None, stack.pop::<i32>());
+    }
+
+    #[test]
+    fn clear_keeps_capacity() {
+        let mut stack = Stack::new();
+        stack.reserve::<i32>(8);
+        let capacity = stack.stack.get_mut().capacity();
+        stack.push(1);
+        stack.clear();
+
+        assert_eq!(None, stack.pop::<i32>());
+        assert_eq!(capacity, stack.stack.get_mut().capacity());
+        assert!(capacity >= 32);
+    }
+}
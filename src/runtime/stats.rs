@@ -0,0 +1,207 @@
+use crate::op_codes::section;
+use crate::runtime::disassembler::parse_binary;
+use crate::runtime::error::RuntimeError;
+use crate::runtime::reader::Reader;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt::Write;
+
+pub struct SectionStats {
+    pub name: &'static str,
+    /// Byte offset of the section id.
+    pub offset: usize,
+    /// Size of the section's contents, as declared in its header.
+    pub size: usize,
+}
+
+pub struct FuncStats {
+    pub index: usize,
+    /// The first name the function is exported under, if any.
+    pub name: Option<String>,
+    /// Size of the body in bytes, including local declarations.
+    pub size: usize,
+    pub locals: usize,
+    pub instrs: usize,
+}
+
+/// Size metrics of a module.
+pub struct Stats {
+    pub size: usize,
+    pub sections: Vec<SectionStats>,
+    pub funcs: Vec<FuncStats>,
+    /// How often each instruction occurs, by mnemonic.
+    pub instrs: BTreeMap<&'static str, usize>,
+}
+
+fn section_name(id: u8) -> &'static str {
+    match id {
+        section::CUSTOM => "custom",
+        section::TYPE => "type",
+        section::FUNC => "function",
+        section::EXPORT => "export",
+        section::CODE => "code",
+        _ => "unknown",
+    }
+}
+
+pub fn stats(binary: Vec<u8>) -> Result<Stats, RuntimeError> {
+    let size = binary.len();
+    let reader = Reader::new(binary);
+    let (module, _) = parse_binary(&reader)?;
+
+    // Walk the section headers again, skipping over the header.
+    let mut sections = vec![];
+    let mut body_sizes = vec![];
+    reader.seek(8);
+    while reader.pos() < size {
+        let offset = reader.pos();
+        let id = reader.byte()?;
+        let section_size = reader.var_u32()? as usize;
+        let start = reader.pos();
+        if id == section::CODE {
+            for _ in 0..reader.byte()? {
+                let body_size = reader.byte()?;
+                body_sizes.push(body_size as usize);
+                reader.bytes(body_size.into())?;
+            }
+        }
+        reader.seek(start + section_size);
+        sections.push(SectionStats {
+            name: section_name(id),
+            offset,
+            size: section_size,
+        });
+    }
+
+    let mut instrs = BTreeMap::new();
+    let funcs = module
+        .funcs
+        .iter()
+        .enumerate()
+        .map(|(index, func)| {
+            for instr in &func.body {
+                *instrs.entry(instr.mnemonic()).or_default() += 1;
+            }
+            let name = module.exports.iter().find(|e| {
+                let crate::ast::EDesc::FuncExport(i) = e.e_desc;
+                i == index
+            });
+            FuncStats {
+                index,
+                name: name.map(|e| e.name.clone()),
+                // Missing when a section's declared size skips past the code.
+                size: body_sizes.get(index).copied().unwrap_or_default(),
+                locals: func.locals.len(),
+                instrs: func.body.len(),
+            }
+        })
+        .collect();
+
+    Ok(Stats {
+        size,
+        sections,
+        funcs,
+        instrs,
+    })
+}
+
+impl Stats {
+    /// The metrics as aligned tables for the terminal.
+    pub fn table(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "module size: {} bytes", self.size);
+
+        let _ = write!(out, "\n{:<10}  {:>8}  {:>8}", "section", "offset", "size");
+        for s in &self.sections {
+            let offset = format!("{:#x}", s.offset);
+            let _ = write!(out, "\n{:<10}  {:>8}  {:>8}", s.name, offset, s.size);
+        }
+
+        let names = self
+            .funcs
+            .iter()
+            .map(|f| f.name.as_deref().unwrap_or("").len());
+        let width = names.chain(Some("name".len())).max().unwrap_or(0);
+        let _ = write!(
+            out,
+            "\n\n{:>5}  {:<width$}  {:>8}  {:>8}  {:>8}",
+            "func", "name", "size", "locals", "instrs"
+        );
+        for f in &self.funcs {
+            let _ = write!(
+                out,
+                "\n{:>5}  {:<width$}  {:>8}  {:>8}  {:>8}",
+                f.index,
+                f.name.as_deref().unwrap_or(""),
+                f.size,
+                f.locals,
+                f.instrs
+            );
+        }
+
+        let _ = write!(out, "\n\n{:<12}  {:>8}", "instruction", "count");
+        for (name, count) in &self.instrs {
+            let _ = write!(out, "\n{:<12}  {:>8}", name, count);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::testing::add_binary;
+
+    #[test]
+    fn stats_of_add() {
+        let stats = stats(add_binary()).unwrap();
+
+        assert_eq!(41, stats.size);
+        let sections: Vec<(&str, usize, usize)> = stats
+            .sections
+            .iter()
+            .map(|s| (s.name, s.offset, s.size))
+            .collect();
+        assert_eq!(
+            vec![
+                ("type", 8, 7),
+                ("function", 17, 2),
+                ("export", 21, 7),
+                ("code", 30, 9)
+            ],
+            sections
+        );
+        assert_eq!(Some("add".to_string()), stats.funcs[0].name);
+        assert_eq!(7, stats.funcs[0].size);
+        assert_eq!(3, stats.funcs[0].instrs);
+        assert_eq!(Some(&2), stats.instrs.get("local.get"));
+        assert_eq!(Some(&1), stats.instrs.get("i32.add"));
+    }
+
+    #[test]
+    fn section_sizes_are_leb128() {
+        let mut binary = add_binary();
+        binary.extend([0x00, 0x8c, 0x01, 0x01, 0x61]); // custom section "a" of 140 bytes
+        binary.extend([0xff; 138]);
+
+        let stats = stats(binary).unwrap();
+
+        let custom = &stats.sections[4];
+        assert_eq!(
+            ("custom", 41, 140),
+            (custom.name, custom.offset, custom.size)
+        );
+    }
+
+    #[test]
+    fn table_lists_every_part() {
+        let table = stats(add_binary()).unwrap().table();
+
+        assert!(table.starts_with("module size: 41 bytes\n"));
+        assert!(table.contains("\ncode            0x1e         9"));
+        assert!(table.contains("\n    0  add          7         0         3"));
+        assert!(table.contains("\nlocal.get            2"));
+    }
+}
@@ -0,0 +1,36 @@
+//! Fixtures shared by the runtime's unit tests.
+
+#[cfg(feature = "std")]
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    rc::Rc,
+};
+
+/// A module exporting `add`, which returns the sum of its two i32 params.
+pub fn add_binary() -> Vec<u8> {
+    vec![
+        0x00, 0x61, 0x73, 0x6d, // magic
+        0x01, 0x00, 0x00, 0x00, // version
+        0x01, 0x07, 0x01, 0x60, 0x02, 0x7f, 0x7f, 0x01, 0x7f, // type section
+        0x03, 0x02, 0x01, 0x00, // function section
+        0x07, 0x07, 0x01, 0x03, 0x61, 0x64, 0x64, 0x00, 0x00, // export section
+        0x0a, 0x09, 0x01, 0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6a, 0x0b, // code section
+    ]
+}
+
+/// An output that hooks can own while the test keeps a handle to read it.
+#[cfg(feature = "std")]
+#[derive(Clone, Default)]
+pub struct Buffer(pub Rc<RefCell<Vec<u8>>>);
+
+#[cfg(feature = "std")]
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
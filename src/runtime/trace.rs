@@ -0,0 +1,94 @@
+use crate::runtime::hooks::{Hooks, Step};
+use crate::runtime::trap::TrapKind;
+use std::io::{stderr, Write};
+
+/// Logs every executed instruction together with the operand stack and
+/// locals as they are before the instruction runs.
+pub struct Tracer {
+    funcs: Vec<String>,
+    out: Box<dyn Write>,
+}
+
+impl Tracer {
+    pub fn new(out: Box<dyn Write>) -> Self {
+        Self {
+            funcs: Vec::new(),
+            out,
+        }
+    }
+
+    pub fn stderr() -> Self {
+        Self::new(Box::new(stderr()))
+    }
+
+    /// Restricts tracing to the given functions. An empty list traces all.
+    pub fn only(mut self, funcs: Vec<String>) -> Self {
+        self.funcs = funcs;
+        self
+    }
+
+    pub fn traces(&self, func: Option<&str>) -> bool {
+        self.funcs.is_empty() || self.funcs.iter().any(|f| Some(f.as_str()) == func)
+    }
+}
+
+impl Hooks for Tracer {
+    fn on_instruction(&mut self, step: &Step) -> Result<(), TrapKind> {
+        if self.traces(step.func.name) {
+            // Tracing is best effort; a broken sink must not abort execution.
+            let _ = writeln!(
+                self.out,
+                "{} @ {:#06x}: {:<12} stack={:?} locals={:?}",
+                step.func.name.unwrap_or("?"),
+                step.offset,
+                step.instr.to_string(),
+                step.stack,
+                step.locals
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Instr;
+    use crate::runtime::hooks::FuncInfo;
+    use crate::runtime::testing::Buffer;
+
+    #[test]
+    fn instr_logs_stack_and_locals() {
+        let buffer = Buffer::default();
+        let mut tracer = Tracer::new(Box::new(buffer.clone()));
+
+        let step = Step {
+            func: FuncInfo {
+                index: 0,
+                name: Some("add"),
+            },
+            pc: 2,
+            offset: 0x25,
+            instr: &Instr::I32Add,
+            stack: &[1, 2],
+            locals: &[1, 2],
+        };
+
+        assert_eq!(Ok(()), tracer.on_instruction(&step));
+
+        assert_eq!(
+            "add @ 0x0025: i32.add      stack=[1, 2] locals=[1, 2]\n",
+            String::from_utf8(buffer.0.borrow().clone()).unwrap()
+        );
+    }
+
+    #[test]
+    fn only_filters_functions() {
+        let tracer = Tracer::stderr().only(vec!["add".to_string()]);
+
+        assert!(tracer.traces(Some("add")));
+        assert!(!tracer.traces(Some("sub")));
+        assert!(!tracer.traces(None));
+        assert!(Tracer::stderr().traces(None));
+    }
+}
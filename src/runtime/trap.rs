@@ -0,0 +1,97 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TrapKind {
+    StackUnderflow,
+    LocalOutOfBounds(usize),
+    Interrupted,
+    OutOfFuel,
+}
+
+/// A single interpreter frame at the time of a trap.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Frame {
+    pub func: usize,
+    /// The name the function is exported under, if any.
+    pub name: Option<String>,
+    /// Byte offset of the trapping instruction in the module.
+    pub offset: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Trap {
+    pub kind: TrapKind,
+    /// Innermost frame first.
+    pub backtrace: Vec<Frame>,
+}
+
+impl fmt::Display for TrapKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrapKind::StackUnderflow => write!(f, "operand stack underflow"),
+            TrapKind::LocalOutOfBounds(i) => write!(f, "local index {} out of bounds", i),
+            TrapKind::Interrupted => write!(f, "execution interrupted"),
+            TrapKind::OutOfFuel => write!(f, "all fuel consumed"),
+        }
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "func[{}]", self.func)?;
+        if let Some(name) = &self.name {
+            write!(f, " <{}>", name)?;
+        }
+        write!(f, " @ {:#x}", self.offset)
+    }
+}
+
+impl core::error::Error for Trap {}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "trap: {}", self.kind)?;
+        write!(f, "backtrace:")?;
+        for (i, frame) in self.backtrace.iter().enumerate() {
+            write!(f, "\n  {}: {}", i, frame)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_trap_with_backtrace() {
+        let trap = Trap {
+            kind: TrapKind::StackUnderflow,
+            backtrace: vec![
+                Frame {
+                    func: 1,
+                    name: None,
+                    offset: 0x2f,
+                },
+                Frame {
+                    func: 0,
+                    name: Some("add".to_string()),
+                    offset: 0x2a,
+                },
+            ],
+        };
+
+        assert_eq!(
+            "trap: operand stack underflow\n\
+             backtrace:\n  \
+             0: func[1] @ 0x2f\n  \
+             1: func[0] <add> @ 0x2a",
+            trap.to_string()
+        );
+    }
+}
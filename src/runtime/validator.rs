@@ -0,0 +1,419 @@
+use crate::ast::*;
+use crate::runtime::disassembler::{parse_binary, CodeMap};
+use crate::runtime::error::RuntimeError;
+use crate::runtime::reader::Reader;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ValidationError {
+    /// The module could not be decoded at all.
+    Decode {
+        offset: usize,
+        error: RuntimeError,
+    },
+    UnknownType {
+        func: usize,
+        index: i32,
+    },
+    UnknownLocal {
+        func: usize,
+        offset: usize,
+        index: usize,
+    },
+    StackUnderflow {
+        func: usize,
+        offset: usize,
+    },
+    TypeMismatch {
+        func: usize,
+        offset: usize,
+        expected: ValueType,
+        found: ValueType,
+    },
+    /// The values left on the stack at `end` differ from the results.
+    ResultMismatch {
+        func: usize,
+        offset: usize,
+        expected: StackType,
+        found: StackType,
+    },
+    UnknownExportFunc {
+        name: String,
+        index: usize,
+    },
+    DuplicateExport {
+        name: String,
+    },
+}
+
+impl core::error::Error for ValidationError {}
+
+impl ValidationError {
+    /// Index of the function the problem is in, if it is in one.
+    pub fn func(&self) -> Option<usize> {
+        match self {
+            ValidationError::UnknownType { func, .. }
+            | ValidationError::UnknownLocal { func, .. }
+            | ValidationError::StackUnderflow { func, .. }
+            | ValidationError::TypeMismatch { func, .. }
+            | ValidationError::ResultMismatch { func, .. } => Some(*func),
+            _ => None,
+        }
+    }
+
+    /// Byte offset in the module the problem was found at, if any.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            ValidationError::Decode { offset, .. }
+            | ValidationError::UnknownLocal { offset, .. }
+            | ValidationError::StackUnderflow { offset, .. }
+            | ValidationError::TypeMismatch { offset, .. }
+            | ValidationError::ResultMismatch { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::Decode { offset, error } => write!(f, "@ {:#x}: {}", offset, error),
+            ValidationError::UnknownType { func, index } => {
+                write!(f, "func[{}]: unknown type {}", func, index)
+            }
+            ValidationError::UnknownLocal {
+                func,
+                offset,
+                index,
+            } => write!(f, "func[{}] @ {:#x}: unknown local {}", func, offset, index),
+            ValidationError::StackUnderflow { func, offset } => {
+                write!(f, "func[{}] @ {:#x}: operand stack underflow", func, offset)
+            }
+            ValidationError::TypeMismatch {
+                func,
+                offset,
+                expected,
+                found,
+            } => write!(
+                f,
+                "func[{}] @ {:#x}: expected {}, found {}",
+                func, offset, expected, found
+            ),
+            ValidationError::ResultMismatch {
+                func,
+                offset,
+                expected,
+                found,
+            } => write!(
+                f,
+                "func[{}] @ {:#x}: expected results {}, found {}",
+                func,
+                offset,
+                list(expected),
+                list(found)
+            ),
+            ValidationError::UnknownExportFunc { name, index } => {
+                write!(f, "export \"{}\": unknown func {}", name, index)
+            }
+            ValidationError::DuplicateExport { name } => {
+                write!(f, "export \"{}\": duplicate name", name)
+            }
+        }
+    }
+}
+
+/// The types as a bracketed list, such as `[i32, i64]`.
+fn list(types: &[ValueType]) -> String {
+    let types: Vec<String> = types.iter().map(ValueType::to_string).collect();
+    format!("[{}]", types.join(", "))
+}
+
+/// Decodes `binary` and checks every function and export, collecting all
+/// problems instead of stopping at the first.
+pub fn validate(binary: Vec<u8>) -> Result<(), Vec<ValidationError>> {
+    let reader = Reader::new(binary);
+    let (module, code_map) = parse_binary(&reader).map_err(|error| {
+        let offset = match (error.offset(), &error) {
+            (Some(offset), _) => offset,
+            (None, RuntimeError::WrongVersionHeader) => 4,
+            (None, RuntimeError::CodeCountMismatch) => reader.pos(),
+            (None, _) => 0,
+        };
+        vec![ValidationError::Decode { offset, error }]
+    })?;
+
+    let errors = validate_module(&module, &code_map);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+pub fn validate_module(module: &Module, code_map: &CodeMap) -> Vec<ValidationError> {
+    let funcs = module
+        .funcs
+        .iter()
+        .enumerate()
+        .flat_map(|(i, func)| validate_func(module, i, func, &code_map[i]));
+
+    let mut errors: Vec<ValidationError> = funcs.collect();
+    for (i, export) in module.exports.iter().enumerate() {
+        let EDesc::FuncExport(index) = export.e_desc;
+        if index >= module.funcs.len() {
+            errors.push(ValidationError::UnknownExportFunc {
+                name: export.name.clone(),
+                index,
+            });
+        }
+        if module.exports[..i].iter().any(|e| e.name == export.name) {
+            errors.push(ValidationError::DuplicateExport {
+                name: export.name.clone(),
+            });
+        }
+    }
+    errors
+}
+
+fn validate_func(
+    module: &Module,
+    index: usize,
+    func: &Func,
+    offsets: &[usize],
+) -> Vec<ValidationError> {
+    let (params, results) = match usize::try_from(func.f_type)
+        .ok()
+        .and_then(|t| module.types.get(t))
+    {
+        Some(t) => t,
+        None => {
+            return vec![ValidationError::UnknownType {
+                func: index,
+                index: func.f_type,
+            }]
+        }
+    };
+    let locals = [params.clone(), func.locals.clone()].concat();
+
+    let mut errors = vec![];
+    let mut stack: StackType = vec![];
+    for (instr, &offset) in func.body.iter().zip(offsets) {
+        match instr {
+            Instr::LocalGet(i) => match locals.get(*i) {
+                Some(vt) => stack.push(*vt),
+                None => {
+                    errors.push(ValidationError::UnknownLocal {
+                        func: index,
+                        offset,
+                        index: *i,
+                    });
+                    // Keep checking the rest as if the local existed.
+                    stack.push(ValueType::I32);
+                }
+            },
+            Instr::I32Add => {
+                for _ in 0..2 {
+                    match stack.pop() {
+                        None => {
+                            errors.push(ValidationError::StackUnderflow {
+                                func: index,
+                                offset,
+                            });
+                            break;
+                        }
+                        Some(ValueType::I32) => {}
+                        Some(found) => errors.push(ValidationError::TypeMismatch {
+                            func: index,
+                            offset,
+                            expected: ValueType::I32,
+                            found,
+                        }),
+                    }
+                }
+                stack.push(ValueType::I32);
+            }
+        }
+    }
+
+    if stack != *results {
+        errors.push(ValidationError::ResultMismatch {
+            func: index,
+            offset: offsets[func.body.len()],
+            expected: results.clone(),
+            found: stack,
+        });
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::testing::add_binary;
+
+    fn module(body: Vec<Instr>, results: StackType) -> Module {
+        Module {
+            types: vec![(vec![ValueType::I32, ValueType::I64], results)],
+            funcs: vec![Func {
+                f_type: 0,
+                locals: vec![],
+                body,
+            }],
+            exports: vec![Export {
+                name: "add".to_string(),
+                e_desc: EDesc::FuncExport(0),
+            }],
+        }
+    }
+
+    #[test]
+    fn valid_add_has_no_errors() {
+        let module = module(
+            vec![Instr::LocalGet(0), Instr::LocalGet(0), Instr::I32Add],
+            vec![ValueType::I32],
+        );
+
+        assert_eq!(
+            Vec::<ValidationError>::new(),
+            validate_module(&module, &vec![vec![35, 37, 39, 40]])
+        );
+    }
+
+    #[test]
+    fn reports_every_problem_with_location() {
+        let mut module = module(
+            vec![Instr::LocalGet(1), Instr::LocalGet(5), Instr::I32Add],
+            vec![],
+        );
+        module.exports.push(module.exports[0].clone());
+
+        assert_eq!(
+            vec![
+                ValidationError::UnknownLocal {
+                    func: 0,
+                    offset: 37,
+                    index: 5
+                },
+                ValidationError::TypeMismatch {
+                    func: 0,
+                    offset: 39,
+                    expected: ValueType::I32,
+                    found: ValueType::I64
+                },
+                ValidationError::ResultMismatch {
+                    func: 0,
+                    offset: 40,
+                    expected: vec![],
+                    found: vec![ValueType::I32]
+                },
+                ValidationError::DuplicateExport {
+                    name: "add".to_string()
+                },
+            ],
+            validate_module(&module, &vec![vec![35, 37, 39, 40]])
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn errors_round_trip_through_json() {
+        let errors = vec![
+            ValidationError::Decode {
+                offset: 8,
+                error: RuntimeError::InvalidArgType {
+                    index: 0,
+                    expected: ValueType::I32,
+                    found: "f64".to_string(),
+                },
+            },
+            ValidationError::DuplicateExport {
+                name: "add".to_string(),
+            },
+        ];
+
+        let json = serde_json::to_string(&errors).unwrap();
+
+        assert_eq!(
+            errors,
+            serde_json::from_str::<Vec<ValidationError>>(&json).unwrap()
+        );
+    }
+
+    #[test]
+    fn locations() {
+        let underflow = ValidationError::StackUnderflow {
+            func: 1,
+            offset: 40,
+        };
+        let duplicate = ValidationError::DuplicateExport {
+            name: "add".to_string(),
+        };
+
+        assert_eq!((Some(1), Some(40)), (underflow.func(), underflow.offset()));
+        assert_eq!((None, None), (duplicate.func(), duplicate.offset()));
+    }
+
+    #[test]
+    fn messages_name_types_as_written() {
+        let mismatch = ValidationError::TypeMismatch {
+            func: 0,
+            offset: 39,
+            expected: ValueType::I32,
+            found: ValueType::I64,
+        };
+        let results = ValidationError::ResultMismatch {
+            func: 0,
+            offset: 40,
+            expected: vec![],
+            found: vec![ValueType::I32, ValueType::I64],
+        };
+
+        assert_eq!(
+            "func[0] @ 0x27: expected i32, found i64",
+            mismatch.to_string()
+        );
+        assert_eq!(
+            "func[0] @ 0x28: expected results [], found [i32, i64]",
+            results.to_string()
+        );
+    }
+
+    #[test]
+    fn decode_errors_are_located_where_the_item_starts() {
+        let truncated = add_binary()[..38].to_vec();
+        let mut oversized = add_binary();
+        oversized[33] += 1; // body size
+        oversized.push(0x0b);
+        oversized[31] += 1; // section size
+
+        let offsets = |binary| match validate(binary) {
+            Err(errors) => errors.iter().map(ValidationError::offset).collect(),
+            Ok(()) => vec![],
+        };
+
+        assert_eq!(vec![Some(38)], offsets(truncated));
+        assert_eq!(vec![Some(34)], offsets(oversized));
+    }
+
+    #[test]
+    fn decode_errors_carry_offset() {
+        let binary = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x05];
+
+        assert_eq!(
+            Err(vec![ValidationError::Decode {
+                offset: 8,
+                error: RuntimeError::InvalidSectionCode {
+                    id: 0x05,
+                    offset: 8
+                }
+            }]),
+            validate(binary)
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use alloc::format;
+use alloc::string::String;
+use core::fmt;
+use core::str::FromStr;
+
+/// A typed value passed to or returned from a function.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Value {
+    I32(i32),
+    I64(i64),
+    F64(f64),
+}
+
+impl Value {
+    /// The value's type as the text format spells it.
+    pub fn ty(&self) -> &'static str {
+        match self {
+            Value::I32(_) => "i32",
+            Value::I64(_) => "i64",
+            Value::F64(_) => "f64",
+        }
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::I32(value)
+    }
+}
+
+/// Parses `42` as an i32, and `i32:42`, `i64:9999999999` or `f64:3.5` as
+/// the type before the colon.
+impl FromStr for Value {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (ty, literal) = s.split_once(':').unwrap_or(("i32", s));
+        let value = match ty {
+            "i32" => literal.parse().map(Value::I32).ok(),
+            "i64" => literal.parse().map(Value::I64).ok(),
+            "f64" => literal.parse().map(Value::F64).ok(),
+            _ => return Err(format!("unknown value type {}", ty)),
+        };
+        value.ok_or_else(|| format!("invalid {} value {}", ty, literal))
+    }
+}
+
+/// Writes an i32 as a plain number and other types with their prefix, so
+/// the output parses back to the same value.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::I32(v) => write!(f, "{}", v),
+            Value::I64(v) => write!(f, "i64:{}", v),
+            Value::F64(v) => write!(f, "f64:{}", v),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_typed_literals() {
+        assert_eq!(Ok(Value::I32(-2)), "-2".parse());
+        assert_eq!(Ok(Value::I32(7)), "i32:7".parse());
+        assert_eq!(Ok(Value::I64(9999999999)), "i64:9999999999".parse());
+        assert_eq!(Ok(Value::F64(3.5)), "f64:3.5".parse());
+        assert_eq!(
+            Err("invalid i32 value 9999999999".to_string()),
+            "9999999999".parse::<Value>()
+        );
+        assert_eq!(
+            Err("unknown value type u8".to_string()),
+            "u8:1".parse::<Value>()
+        );
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for value in [Value::I32(-2), Value::I64(1 << 40), Value::F64(0.5)] {
+            assert_eq!(Ok(value), value.to_string().parse());
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use zod::runtime::{self, Config, Instance};
+use zod::{compiler, parser};
+
+/// A call and what it has to return, or the message of the error it has
+/// to fail with.
+type Call = (&'static str, &'static [i32], Result<i32, &'static str>);
+
+/// A module embedded in the binary with the calls to check against it.
+struct Case {
+    name: &'static str,
+    source: &'static str,
+    calls: &'static [Call],
+}
+
+const CASES: &[Case] = &[
+    Case {
+        name: "empty module",
+        source: "(module)",
+        calls: &[("add", &[], Err("no export named \"add\""))],
+    },
+    Case {
+        name: "local.get",
+        source: r#"(module
+            (func $first (param i32) (param i32) (result i32) local.get 0)
+            (func $second (param $a i32) (param $b i32) (result i32) local.get $b)
+            (export "first" (func $first))
+            (export "second" (func $second)))"#,
+        calls: &[
+            ("first", &[7, 9], Ok(7)),
+            ("second", &[7, 9], Ok(9)),
+            ("second", &[-1, i32::MIN], Ok(i32::MIN)),
+        ],
+    },
+    Case {
+        name: "i32.add",
+        source: r#"(module
+            (func $add (param i32) (param i32) (result i32)
+                local.get 0 local.get 1 i32.add)
+            (export "add" (func $add)))"#,
+        calls: &[
+            ("add", &[1, 2], Ok(3)),
+            ("add", &[-5, 3], Ok(-2)),
+            ("add", &[i32::MAX, 1], Ok(i32::MIN)),
+            ("add", &[1], Err("wrong number of arguments")),
+        ],
+    },
+    Case {
+        name: "nested adds",
+        source: r#"(module
+            (func $sum (param i32) (param i32) (param i32) (result i32)
+                local.get 0 local.get 1 i32.add local.get 2 i32.add)
+            (func $double (param i32) (result i32)
+                local.get 0 local.get 0 i32.add)
+            (export "sum" (func $sum))
+            (export "double" (func $double))
+            (export "twice" (func $double)))"#,
+        calls: &[
+            ("sum", &[1, 2, 3], Ok(6)),
+            ("double", &[21], Ok(42)),
+            ("twice", &[-4], Ok(-8)),
+        ],
+    },
+];
+
+/// Runs one case through parse, compile, validate, decode and execute,
+/// describing the first thing that goes wrong.
+fn check(case: &Case) -> Result<(), String> {
+    let ast = parser::parse(case.source).map_err(|e| e.to_string())?;
+    let binary = compiler::compile(&ast);
+    runtime::validate(binary.clone()).map_err(|errors| {
+        let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        errors.join("; ")
+    })?;
+
+    let (decoded, _) = runtime::disassemble(binary.clone()).map_err(|e| e.to_string())?;
+    if decoded != ast {
+        return Err("decoded module differs from the parsed one".to_string());
+    }
+
+    let mut instance = Instance::new(binary, Config::default()).map_err(|e| e.to_string())?;
+    for (func, args, expected) in case.calls {
+        let result = instance.invoke(func, args).map_err(|e| e.to_string());
+        if result != expected.map_err(String::from) {
+            return Err(format!(
+                "{} {:?}: expected {:?}, got {:?}",
+                func, args, expected, result
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks every embedded case, returning each one's name and outcome.
+pub fn run() -> Vec<(&'static str, Result<(), String>)> {
+    CASES.iter().map(|case| (case.name, check(case))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_case_passes() {
+        for (name, result) in run() {
+            assert_eq!(Ok(()), result, "{}", name);
+        }
+    }
+
+    #[test]
+    fn wrong_expectations_are_reported() {
+        let case = Case {
+            name: "wrong",
+            calls: &[("add", &[1, 2], Ok(4))],
+            ..CASES[2]
+        };
+
+        assert_eq!(
+            Err("add [1, 2]: expected Ok(4), got Ok(3)".to_string()),
+            check(&case)
+        );
+    }
+}